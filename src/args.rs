@@ -1,26 +1,279 @@
-use std::panic;
-use std::time::SystemTime;
-
 use chrono::{DateTime, Utc};
-use chrono_tz::Tz;
 
-use crate::dump::{get_fn, DumpOutcomeFn};
+use crate::config::{Config, Source};
+use crate::dump::{self, Formatter, Record, Sink, FORMATTERS};
+use crate::expr;
+use crate::id::{parse_epoch, DEFAULT_EPOCH};
+use crate::preset::{get_preset, in_utc, PRESETS};
+use crate::process::{check_fmt, check_parse_fmt, FmtDate, FmtError};
+use crate::relative::Relative;
+use crate::select::Select;
+use crate::unit::Unit;
+use crate::value::ValueOptions;
+use crate::window::Window;
+use crate::zone::{suggest, Zone};
+
+// An option, known by its long name, with an optional one letter alias.
+#[derive(Debug)]
+struct Opt {
+    short: Option<char>,
+    long: &'static str,
+    takes_value: bool,
+}
+impl Opt {
+    const fn flag(short: Option<char>, long: &'static str) -> Opt {
+        Opt {
+            short,
+            long,
+            takes_value: false,
+        }
+    }
 
-const JSON_FLAGS: &[&str] = &["-j", "--json"];
+    const fn value(short: Option<char>, long: &'static str) -> Opt {
+        Opt {
+            short,
+            long,
+            takes_value: true,
+        }
+    }
+}
+
+const HELP: Opt = Opt::flag(Some('h'), "help");
+const FORMAT: Opt = Opt::value(Some('F'), "format");
+const TZ: Opt = Opt::value(Some('T'), "tz");
+const LENIENT_TZ: Opt = Opt::flag(None, "lenient-tz");
+const FILE: Opt = Opt::value(Some('f'), "file");
+const JSON: Opt = Opt::flag(Some('j'), "json");
+const OUTPUT: Opt = Opt::value(None, "output");
+const STRICT: Opt = Opt::flag(None, "strict");
+const FILTER: Opt = Opt::flag(None, "filter");
+const ANNOTATE: Opt = Opt::flag(None, "annotate");
+const AUTO: Opt = Opt::flag(None, "auto");
+const WINDOW: Opt = Opt::value(None, "window");
+const RELATIVE: Opt = Opt::flag(None, "relative");
+const RELATIVE_FULL: Opt = Opt::flag(None, "relative-full");
+const REF: Opt = Opt::value(None, "ref");
+const REVERSE: Opt = Opt::flag(Some('r'), "reverse");
+const UNIT: Opt = Opt::value(Some('U'), "unit");
+const SHOW_CONFIG: Opt = Opt::flag(None, "show-config");
+const LIST_FORMATS: Opt = Opt::flag(None, "list-formats");
+const PAIRS: Opt = Opt::flag(None, "pairs");
+const DELTA: Opt = Opt::flag(None, "delta");
+const STATS: Opt = Opt::flag(None, "stats");
+const SORT: Opt = Opt::flag(None, "sort");
+const DEDUPE: Opt = Opt::flag(None, "dedupe");
+const AFTER: Opt = Opt::value(None, "after");
+const BEFORE: Opt = Opt::value(None, "before");
+const ID: Opt = Opt::flag(None, "id");
+const EPOCH: Opt = Opt::value(None, "epoch");
+const OPTS: &[Opt] = &[
+    HELP,
+    FORMAT,
+    TZ,
+    LENIENT_TZ,
+    FILE,
+    JSON,
+    OUTPUT,
+    STRICT,
+    FILTER,
+    ANNOTATE,
+    AUTO,
+    WINDOW,
+    RELATIVE,
+    RELATIVE_FULL,
+    REF,
+    REVERSE,
+    UNIT,
+    SHOW_CONFIG,
+    LIST_FORMATS,
+    PAIRS,
+    DELTA,
+    STATS,
+    SORT,
+    DEDUPE,
+    AFTER,
+    BEFORE,
+    ID,
+    EPOCH,
+];
+// Options of conversions, that other modes would silently ignore.
+const CONVERSION_OPTS: &[Opt] = &[
+    SORT,
+    DEDUPE,
+    AFTER,
+    BEFORE,
+    DELTA,
+    STATS,
+    ID,
+    EPOCH,
+    RELATIVE,
+    RELATIVE_FULL,
+    REF,
+];
+// The other options each mode doesn't use.
+const DIFF_IGNORED: &[Opt] = &[FORMAT, FILTER, ANNOTATE, REVERSE, UNIT];
+const FILTER_IGNORED: &[Opt] = &[JSON, OUTPUT, STRICT, AUTO, REVERSE, UNIT, PAIRS];
+const REVERSE_IGNORED: &[Opt] = &[AUTO, WINDOW, PAIRS];
+const CONVERSION_IGNORED: &[Opt] = &[UNIT, PAIRS];
+const DIFF: &str = "diff";
+const COMMANDS: &[&str] = &[DIFF];
 const DEFAULT_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S %z";
 const DEFAULT_TZ: &str = "UTC";
 
 #[derive(Debug, PartialEq)]
 pub enum ArgsError {
     NotEnough(String),
+    File(String),
+    UnknownTz(String),
+    UnknownOutput(String),
+    InvalidOption(String),
+    InvalidFormat(String),
 }
 impl std::fmt::Display for ArgsError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ArgsError::NotEnough(msg) => write!(f, "{msg}"),
+            ArgsError::File(msg) => write!(f, "{msg}"),
+            ArgsError::UnknownTz(msg) => write!(f, "{msg}"),
+            ArgsError::UnknownOutput(msg) => write!(f, "{msg}"),
+            ArgsError::InvalidOption(msg) => write!(f, "{msg}"),
+            ArgsError::InvalidFormat(msg) => write!(f, "{msg}"),
         }
     }
 }
+impl std::error::Error for ArgsError {}
+
+/*
+ * The command line once parsed: the options found, in order, with their value,
+ * the subcommand if any, and `args`, the program name followed by the positional values.
+ * `config` holds the defaults of the options not given.
+*/
+#[derive(Debug, PartialEq)]
+pub struct Cli {
+    found: Vec<(&'static str, Option<String>)>,
+    command: Option<&'static str>,
+    pub args: Vec<String>,
+    config: Config,
+}
+impl Cli {
+    pub fn with_config(self, config: Config) -> Cli {
+        Cli { config, ..self }
+    }
+
+    fn flag(&self, opt: &Opt) -> bool {
+        self.found.iter().any(|(long, _)| *long == opt.long)
+    }
+
+    // The last one wins.
+    fn value(&self, opt: &Opt) -> Option<String> {
+        self.values(opt).pop()
+    }
+
+    fn values(&self, opt: &Opt) -> Vec<String> {
+        self.found
+            .iter()
+            .filter(|(long, _)| *long == opt.long)
+            .filter_map(|(_, value)| value.to_owned())
+            .collect()
+    }
+}
+
+/*
+ * Long options take their value as `--tz=UTC` or `--tz UTC`, short ones as `-TUTC` or `-T UTC`.
+ * Short flags can be combined (`-jr`), `--` ends the options, and a dash followed
+ * by a digit, or by a unit prefix and a digit, is a value, not an option:
+ * negative timestamps (`-86400`, `-m1000`) are positional values.
+ * A subcommand (`diff`) is the first positional value, anywhere among the options.
+*/
+pub fn parse(cli_args: Vec<String>) -> Result<Cli, ArgsError> {
+    let mut cli_args = cli_args.into_iter();
+    let mut cli = Cli {
+        found: Vec::new(),
+        command: None,
+        args: cli_args.next().into_iter().collect(),
+        config: Config::default(),
+    };
+    while let Some(arg) = cli_args.next() {
+        if arg == "--" {
+            cli.args.extend(cli_args);
+            break;
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            let opt = OPTS
+                .iter()
+                .find(|x| x.long == name)
+                .ok_or_else(|| unknown_option(&format!("--{name}")))?;
+            let value = match (opt.takes_value, value) {
+                (true, Some(value)) => Some(value),
+                (true, None) => Some(cli_args.next().ok_or_else(|| missing_value(&arg))?),
+                (false, Some(_)) => {
+                    return Err(ArgsError::InvalidOption(format!(
+                        "unexpected value for option: '--{name}'"
+                    )))
+                }
+                (false, None) => None,
+            };
+            cli.found.push((opt.long, value));
+        } else if is_option(&arg) {
+            for (idx, c) in arg.char_indices().skip(1) {
+                let opt = OPTS
+                    .iter()
+                    .find(|x| x.short == Some(c))
+                    .ok_or_else(|| unknown_option(&format!("-{c}")))?;
+                if !opt.takes_value {
+                    cli.found.push((opt.long, None));
+                    continue;
+                }
+                let value = match &arg[idx + c.len_utf8()..] {
+                    "" => cli_args
+                        .next()
+                        .ok_or_else(|| missing_value(&format!("-{c}")))?,
+                    rest => rest.to_string(),
+                };
+                cli.found.push((opt.long, Some(value)));
+                break;
+            }
+        } else if let Some(command) = COMMANDS
+            .iter()
+            .find(|x| **x == arg && cli.command.is_none() && cli.args.len() == 1)
+        {
+            cli.command = Some(command);
+        } else {
+            cli.args.push(arg);
+        }
+    }
+    Ok(cli)
+}
+
+// `-` alone stands for stdin, `-86400` and `-m1000` are values.
+fn is_option(arg: &str) -> bool {
+    let Some(rest) = arg.strip_prefix('-') else {
+        return false;
+    };
+    let mut chars = rest.chars();
+    let is_value = match chars.next() {
+        None => true,
+        Some(c) if c.is_ascii_digit() => true,
+        Some(c) => {
+            c.is_ascii_lowercase()
+                && c.to_string().parse::<Unit>().is_ok()
+                && chars.next().is_some_and(|c| c.is_ascii_digit())
+        }
+    };
+    !is_value
+}
+
+fn unknown_option(option: &str) -> ArgsError {
+    ArgsError::InvalidOption(format!("unknown option: '{option}'"))
+}
+
+fn missing_value(option: &str) -> ArgsError {
+    ArgsError::InvalidOption(format!("missing value for option: '{option}'"))
+}
 
 pub fn get_ts_strings(mut args: Vec<String>) -> Result<Vec<String>, ArgsError> {
     let count = args.len() - 1;
@@ -32,95 +285,441 @@ pub fn get_ts_strings(mut args: Vec<String>) -> Result<Vec<String>, ArgsError> {
     }
 }
 
-pub fn get_dump_fn(cli_args: &mut Vec<String>) -> DumpOutcomeFn {
-    let mut json = false;
-    let original_len = cli_args.len();
+pub fn get_help(cli: &Cli) -> bool {
+    cli.flag(&HELP)
+}
+
+pub fn get_sink<R: Record>(cli: &Cli) -> Result<Box<dyn Sink<R>>, ArgsError> {
+    Ok(get_formatter(cli)?.sink(Box::new(std::io::stdout())))
+}
+
+// `--output` wins over the JSON flags, kept as a shortcut.
+fn output_setting(cli: &Cli) -> (String, Source) {
+    match cli.value(&OUTPUT) {
+        Some(name) => (name, Source::Cli),
+        None if cli.flag(&JSON) => ("json".to_string(), Source::Cli),
+        None => setting(cli, &OUTPUT, "text"),
+    }
+}
 
-    // check if json output is required
-    cli_args.retain(|x| !JSON_FLAGS.contains(&x.as_str()));
-    if original_len > cli_args.len() {
-        json = true;
+// The option, or its configured default, or the hard-coded one.
+fn setting(cli: &Cli, opt: &Opt, default: &str) -> (String, Source) {
+    match cli.value(opt) {
+        Some(value) => (value, Source::Cli),
+        None => cli
+            .config
+            .get(opt.long)
+            .unwrap_or_else(|| (default.to_string(), Source::Default)),
     }
-    get_fn(json)
 }
 
-pub fn get_fmt_str(cli_args: &mut Vec<String>) -> String {
-    validate_fmt(extract_fmt(cli_args))
+fn get_formatter(cli: &Cli) -> Result<&'static Formatter, ArgsError> {
+    let (name, _) = output_setting(cli);
+    dump::get_formatter(&name).ok_or_else(|| {
+        let names: Vec<&str> = FORMATTERS.iter().map(|x| x.name).collect();
+        ArgsError::UnknownOutput(format!(
+            "unknown output format: '{name}', expected one of {}",
+            names.join(", ")
+        ))
+    })
 }
 
-fn extract_fmt(cli_args: &mut Vec<String>) -> String {
-    let mut cli_args_iter = cli_args.clone().into_iter();
-    match cli_args_iter.position(|x| x == "-F") {
-        None => DEFAULT_DATE_FORMAT.to_string(),
-        Some(idx) => {
-            cli_args.remove(idx);
-            if let Some(fmt) = cli_args_iter.next() {
-                cli_args.remove(idx);
-                return fmt.to_owned();
-            }
-            DEFAULT_DATE_FORMAT.to_string()
+pub fn get_files(cli: &Cli) -> Vec<String> {
+    cli.values(&FILE)
+}
+
+pub fn get_filter(cli: &Cli) -> bool {
+    cli.flag(&FILTER)
+}
+
+pub fn get_annotate(cli: &Cli) -> bool {
+    cli.flag(&ANNOTATE)
+}
+
+pub fn get_auto(cli: &Cli) -> bool {
+    cli.flag(&AUTO)
+}
+
+pub fn get_reverse(cli: &Cli) -> bool {
+    cli.flag(&REVERSE)
+}
+
+/*
+ * diff, --filter, --annotate and --reverse don't convert values one by one,
+ * the options of conversions are usage errors there, like those of other modes.
+ * --annotate being a kind of --filter, both can be given.
+*/
+pub fn check_mode(cli: &Cli) -> Result<(), ArgsError> {
+    let (mode, ignored) = if get_diff(cli) {
+        (DIFF, DIFF_IGNORED)
+    } else if get_filter(cli) {
+        ("--filter", FILTER_IGNORED)
+    } else if get_annotate(cli) {
+        ("--annotate", FILTER_IGNORED)
+    } else if get_reverse(cli) {
+        ("--reverse", REVERSE_IGNORED)
+    } else {
+        ("conversions", CONVERSION_IGNORED)
+    };
+    let conversion_opts = if mode == "conversions" {
+        &[]
+    } else {
+        CONVERSION_OPTS
+    };
+    let mut opts = conversion_opts.iter().chain(ignored);
+    match opts.find(|opt| cli.flag(opt)) {
+        Some(opt) => Err(ArgsError::InvalidOption(format!(
+            "option '--{}' can't be used with {mode}",
+            opt.long
+        ))),
+        None => Ok(()),
+    }
+}
+
+pub fn get_show_config(cli: &Cli) -> bool {
+    cli.flag(&SHOW_CONFIG)
+}
+
+/*
+ * The effective settings, as a config file would hold them, with where they come from.
+ * Values are quoted as JSON strings, which are valid TOML basic strings.
+*/
+pub fn show_config(cli: &Cli) -> String {
+    let path = match &cli.config.path {
+        Some(path) if path.exists() => path.display().to_string(),
+        Some(path) => format!("{} (not found)", path.display()),
+        None => "none".to_string(),
+    };
+    let (tzs, tz_source) = tz_setting(cli);
+    let settings = [
+        ("format", fmt_setting(cli)),
+        ("tz", (tzs.join(","), tz_source)),
+        ("output", output_setting(cli)),
+    ];
+    let mut lines = vec![format!("# config file: {path}")];
+    for (key, (value, source)) in settings {
+        let value = serde_json::Value::String(value);
+        lines.push(format!("{key} = {value}  # {source}"));
+    }
+    lines.join("\n")
+}
+
+pub fn get_diff(cli: &Cli) -> bool {
+    cli.command == Some(DIFF)
+}
+
+pub fn get_pairs(cli: &Cli) -> bool {
+    cli.flag(&PAIRS)
+}
+
+pub fn get_delta(cli: &Cli) -> bool {
+    cli.flag(&DELTA)
+}
+
+pub fn get_stats(cli: &Cli) -> bool {
+    cli.flag(&STATS)
+}
+
+pub fn get_strict(cli: &Cli) -> bool {
+    cli.flag(&STRICT)
+}
+
+pub fn get_unit(cli: &Cli) -> Unit {
+    match cli.value(&UNIT) {
+        None => Unit::Secs,
+        Some(unit) => validate_unit(unit),
+    }
+}
+
+// A unit to convert dates to can't be guessed.
+fn validate_unit(unit: String) -> Unit {
+    match unit.parse::<Unit>() {
+        Ok(Unit::Auto) | Err(_) => {
+            eprintln!("! invalid unit: '{unit}', using seconds.");
+            Unit::Secs
+        }
+        Ok(u) => u,
+    }
+}
+
+// A reference without --relative* implies --relative, it can be an expression (`now-1d`).
+pub fn get_relative(cli: &Cli, opts: &ValueOptions, tz: &Zone) -> Option<Relative> {
+    let largest = cli.flag(&RELATIVE);
+    let full = cli.flag(&RELATIVE_FULL);
+    let reference = cli.value(&REF);
+    if !largest && !full && reference.is_none() {
+        return None;
+    }
+    let reference = reference.and_then(|r| validate_ref(r, opts, tz));
+    Some(Relative::new(reference, full))
+}
+
+fn validate_ref(reference: String, opts: &ValueOptions, tz: &Zone) -> Option<DateTime<Utc>> {
+    match expr::eval(&reference, opts, tz) {
+        Ok(parsed) => Some(parsed.dt),
+        Err(err) => {
+            eprintln!("! invalid reference: '{reference}' ({err}), using now.");
+            None
         }
     }
 }
 
-fn validate_fmt(fmt: String) -> String {
-    let dt_now: DateTime<Utc> = SystemTime::now().into();
-    let fmt_ts = dt_now.format(fmt.as_str());
-    let result = panic::catch_unwind(|| format!("{fmt_ts}"));
-    match result {
-        Ok(_) => fmt,
+// Bounds take any value or expression; an invalid one would select the wrong values.
+pub fn get_select(cli: &Cli, opts: &ValueOptions, tz: &Zone) -> Result<Select, ArgsError> {
+    let bound = |opt: &Opt| match cli.value(opt) {
+        Some(bound) => match expr::eval(&bound, opts, tz) {
+            Ok(parsed) => Ok(Some(parsed.dt)),
+            Err(err) => Err(ArgsError::InvalidOption(format!(
+                "invalid value for option '--{}': '{bound}' ({err})",
+                opt.long
+            ))),
+        },
+        None => Ok(None),
+    };
+    Ok(Select {
+        after: bound(&AFTER)?,
+        before: bound(&BEFORE)?,
+        sort: cli.flag(&SORT),
+        dedupe: cli.flag(&DEDUPE),
+    })
+}
+
+// The epoch of snowflakes when values are IDs, a custom epoch implying `--id`.
+pub fn get_ids(cli: &Cli) -> Result<Option<DateTime<Utc>>, ArgsError> {
+    let epoch = match cli.value(&EPOCH) {
+        Some(epoch) => epoch,
+        None if cli.flag(&ID) => DEFAULT_EPOCH.to_string(),
+        None => return Ok(None),
+    };
+    parse_epoch(&epoch).map(Some).map_err(|err| {
+        ArgsError::InvalidOption(format!(
+            "invalid value for option '--{}': '{epoch}' ({err})",
+            EPOCH.long
+        ))
+    })
+}
+
+pub fn get_window(cli: &Cli) -> Window {
+    match cli.value(&WINDOW) {
+        None => Window::default(),
+        Some(window) => validate_window(window),
+    }
+}
+
+fn validate_window(window: String) -> Window {
+    match window.parse::<Window>() {
+        Ok(w) => w,
         Err(_) => {
-            eprintln!("! invalid format string: '{fmt}', using default.");
-            DEFAULT_DATE_FORMAT.to_string()
+            eprintln!("! invalid window: '{window}', using default.");
+            Window::default()
         }
     }
 }
 
-pub fn get_tz(cli_args: &mut Vec<String>) -> chrono_tz::Tz {
-    validate_tz(extract_tz(cli_args))
+fn fmt_setting(cli: &Cli) -> (String, Source) {
+    setting(cli, &FORMAT, DEFAULT_DATE_FORMAT)
 }
 
-fn extract_tz(cli_args: &mut Vec<String>) -> String {
-    let mut cli_args_iter = cli_args.clone().into_iter();
-    match cli_args_iter.position(|x| x == "-T") {
-        None => DEFAULT_TZ.to_string(),
-        Some(idx) => {
-            cli_args.remove(idx);
-            if let Some(tz) = cli_args_iter.next() {
-                cli_args.remove(idx);
-                return tz.to_owned();
-            }
-            DEFAULT_TZ.to_string()
+pub fn get_fmt_str(cli: &Cli) -> Result<String, ArgsError> {
+    // --reverse only parses dates with the format, unless --filter or --annotate win
+    let check = if get_reverse(cli) && !get_filter(cli) && !get_annotate(cli) {
+        check_parse_fmt
+    } else {
+        check_fmt
+    };
+    match fmt_setting(cli) {
+        (fmt, Source::Default) => Ok(fmt),
+        (fmt, _) => validate_fmt(resolve_fmt(cli, fmt), get_strict(cli), check),
+    }
+}
+
+// A format is an alias from the configuration, a preset or a strftime string.
+fn resolve_fmt(cli: &Cli, fmt: String) -> String {
+    let fmt = cli.config.formats().get(&fmt).cloned().unwrap_or(fmt);
+    match get_preset(&fmt) {
+        Some(preset) => preset.fmt.to_string(),
+        None => fmt,
+    }
+}
+
+pub fn get_list_formats(cli: &Cli) -> bool {
+    cli.flag(&LIST_FORMATS)
+}
+
+// Presets then the configured aliases, with their format and `now` rendered in `tz`.
+pub fn list_formats(cli: &Cli, tz: &Zone, now: DateTime<Utc>) -> String {
+    let mut rows: Vec<(String, String)> = PRESETS
+        .iter()
+        .map(|preset| match preset.aliases {
+            [] => (preset.name.to_string(), preset.fmt.to_string()),
+            aliases => (
+                format!("{} ({})", preset.name, aliases.join(", ")),
+                preset.fmt.to_string(),
+            ),
+        })
+        .collect();
+    for name in cli.config.formats().keys() {
+        rows.push((
+            format!("{name} (config)"),
+            resolve_fmt(cli, name.to_owned()),
+        ));
+    }
+    let name_width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let fmt_width = rows.iter().map(|(_, fmt)| fmt.len()).max().unwrap_or(0);
+    rows.into_iter()
+        .map(|(name, fmt)| {
+            let tz = if in_utc(&fmt) {
+                Zone::from(chrono_tz::UTC)
+            } else {
+                tz.clone()
+            };
+            let sample = match check_fmt(&fmt) {
+                Ok(()) => FmtDate::new(now, fmt.to_owned(), tz).to_string(),
+                Err(err) => format!("! {err}"),
+            };
+            format!("{name:name_width$}  {fmt:fmt_width$}  {sample}")
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// An invalid format falls back to the default, unless --strict is given.
+fn validate_fmt(
+    fmt: String,
+    strict: bool,
+    check: fn(&str) -> Result<(), FmtError>,
+) -> Result<String, ArgsError> {
+    match check(&fmt) {
+        Ok(()) => Ok(fmt),
+        Err(err) if strict => Err(ArgsError::InvalidFormat(format!(
+            "invalid format string: '{fmt}': {err}"
+        ))),
+        Err(err) => {
+            eprintln!("! invalid format string: '{fmt}': {err}, using default.");
+            Ok(DEFAULT_DATE_FORMAT.to_string())
         }
     }
 }
 
-fn validate_tz(tz_str: String) -> Tz {
-    match tz_str.parse::<Tz>() {
-        Ok(tz) => tz,
-        Err(_) => DEFAULT_TZ.parse::<Tz>().unwrap(),
+// -T can be repeated, the configuration holds a single value.
+fn tz_setting(cli: &Cli) -> (Vec<String>, Source) {
+    let tzs = cli.values(&TZ);
+    if tzs.is_empty() {
+        let (tz, source) = setting(cli, &TZ, DEFAULT_TZ);
+        (vec![tz], source)
+    } else {
+        (tzs, Source::Cli)
     }
 }
 
-pub fn cleanup(cli_args: &mut Vec<String>) -> bool {
-    let original_len = cli_args.len();
-    let mut cleaned = false;
-    cli_args.retain(|x| !x.starts_with("-"));
-    if original_len > cli_args.len() {
-        cleaned = true;
+/*
+ * Each -T value can be a comma separated list.
+ * An unknown timezone is an error, unless --lenient-tz is given.
+ * Formats writing the zone as text (`http`) are only rendered in UTC.
+*/
+pub fn get_tzs(cli: &Cli) -> Result<Vec<Zone>, ArgsError> {
+    let lenient = cli.flag(&LENIENT_TZ);
+    let mut tzs: Vec<Zone> = Vec::new();
+    for tzs_str in tz_setting(cli).0 {
+        for tz_str in split_tzs(&tzs_str) {
+            tzs.push(validate_tz(tz_str, lenient)?);
+        }
+    }
+    let fmt = resolve_fmt(cli, fmt_setting(cli).0);
+    if in_utc(&fmt) && !tzs.iter().all(Zone::is_utc) {
+        eprintln!("! the format '{fmt}' is always in UTC, ignoring timezones.");
+        tzs = vec![Zone::from(chrono_tz::UTC)];
+    }
+    Ok(tzs)
+}
+
+// Commas separate timezones, except those introducing the rules of a POSIX TZ string.
+fn split_tzs(tzs_str: &str) -> Vec<String> {
+    let mut tz_strs: Vec<String> = Vec::new();
+    for part in tzs_str.split(',').map(|x| x.trim()) {
+        let mut chars = part.chars();
+        let is_rule = match chars.next() {
+            Some('M') | Some('J') => chars.next().is_some_and(|c| c.is_ascii_digit()),
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        };
+        match tz_strs.last_mut() {
+            Some(last) if is_rule => {
+                last.push(',');
+                last.push_str(part);
+            }
+            _ => tz_strs.push(part.to_string()),
+        }
+    }
+    tz_strs
+}
+
+fn validate_tz(tz_str: String, lenient: bool) -> Result<Zone, ArgsError> {
+    match tz_str.parse::<Zone>() {
+        Ok(tz) => Ok(tz),
+        Err(_) if lenient => {
+            eprintln!("! unknown timezone: '{tz_str}', using default.");
+            Ok(DEFAULT_TZ.parse::<Zone>().unwrap())
+        }
+        Err(_) => {
+            let mut msg = format!("unknown timezone: '{tz_str}'");
+            let suggestions = suggest(&tz_str);
+            if !suggestions.is_empty() {
+                msg.push_str(&format!(", did you mean {}?", suggestions.join(", ")));
+            }
+            Err(ArgsError::UnknownTz(msg))
+        }
     }
-    cleaned
 }
 
 #[cfg(test)]
 mod test {
     use chrono_tz::UTC;
 
-    use crate::args::ArgsError;
-    use crate::args::DEFAULT_DATE_FORMAT;
-    use crate::args::{cleanup, get_dump_fn};
+    use crate::zone::Zone;
+
+    use crate::args::{check_mode, get_show_config, show_config};
+    use crate::args::{get_annotate, get_auto, get_diff, get_filter, get_pairs, get_window};
+    use crate::args::{get_files, get_formatter, get_help, get_ids, get_sink};
     use crate::args::{get_fmt_str, get_ts_strings, validate_fmt};
-    use crate::args::{get_tz, validate_tz};
+    use crate::args::{get_list_formats, list_formats};
+    use crate::args::{get_relative, get_reverse, get_select, get_strict, get_unit};
+    use crate::args::{get_tzs, split_tzs, validate_tz};
+    use crate::args::{parse, ArgsError, Cli};
+    use crate::args::{DEFAULT_DATE_FORMAT, FORMAT, JSON, REVERSE, TZ};
+    use crate::config::{load_from, Config};
+    use crate::outcome::Outcome;
+    use crate::process::check_fmt;
+    use crate::select::Select;
+    use crate::unit::Unit;
+    use crate::value::ValueOptions;
+    use crate::window::Window;
+
+    fn cli(args: &[&str]) -> Cli {
+        let mut cli_args = vec!["tsp".to_string()];
+        cli_args.extend(args.iter().map(|x| x.to_string()));
+        parse(cli_args).unwrap()
+    }
+
+    // A configuration from the environment only, as `TSP_<KEY>=<value>` pairs.
+    fn env_config(vars: &[(&str, &str)]) -> Config {
+        let vars: Vec<(String, String)> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.to_owned())
+        };
+        load_from(None, var).unwrap()
+    }
+
+    fn parse_err(args: &[&str]) -> ArgsError {
+        let mut cli_args = vec!["tsp".to_string()];
+        cli_args.extend(args.iter().map(|x| x.to_string()));
+        parse(cli_args).unwrap_err()
+    }
 
     #[test]
     fn test_no_args() {
@@ -141,133 +740,631 @@ mod test {
     }
 
     #[test]
-    fn test_get_dump_fn_text() {
-        // can't test the value returned by `get_dump_fn`, as it will be a private function
-        // we can test other effect of the function on the arguments passed to it.
-        let mut some_args: Vec<String> = vec!["tsp".to_string(), "argA".to_string()];
-        let _ = get_dump_fn(&mut some_args);
-        assert_eq!(some_args, vec!["tsp".to_string(), "argA".to_string()]);
+    fn test_parse_values() {
+        let cli = cli(&["argA", "-T", "Europe/Paris", "argB"]);
+        assert_eq!(cli.args, vec!["tsp", "argA", "argB"]);
+        assert_eq!(cli.value(&TZ), Some("Europe/Paris".to_string()));
     }
 
     #[test]
-    fn test_get_dump_fn_json() {
-        // same as above
-        let mut some_args: Vec<String> =
-            vec!["tsp".to_string(), "--json".to_string(), "argA".to_string()];
-        let _ = get_dump_fn(&mut some_args);
-        assert_eq!(some_args, vec!["tsp".to_string(), "argA".to_string()]);
+    fn test_parse_long_with_equal() {
+        let cli = cli(&["--format=%Y=%m", "--tz", "UTC", "--json"]);
+        assert_eq!(cli.value(&FORMAT), Some("%Y=%m".to_string()));
+        assert_eq!(cli.value(&TZ), Some("UTC".to_string()));
+        assert!(cli.flag(&JSON));
+        assert_eq!(cli.args, vec!["tsp"]);
     }
 
     #[test]
-    fn test_get_dump_fn_json_short() {
-        // same as above
-        let mut some_args: Vec<String> =
-            vec!["tsp".to_string(), "-j".to_string(), "argA".to_string()];
-        let _ = get_dump_fn(&mut some_args);
-        assert_eq!(some_args, vec!["tsp".to_string(), "argA".to_string()]);
+    fn test_parse_combined_short() {
+        let cli = cli(&["-jrF%Y", "-T", "UTC", "-jTAsia/Tokyo"]);
+        assert!(cli.flag(&JSON));
+        assert!(cli.flag(&REVERSE));
+        assert_eq!(cli.value(&FORMAT), Some("%Y".to_string()));
+        assert_eq!(cli.values(&TZ), vec!["UTC", "Asia/Tokyo"]);
     }
 
     #[test]
-    fn test_get_fmt_str() {
-        let mut some_args: Vec<String> = vec![
-            "tsp".to_string(),
-            "-F".to_string(),
-            "a-format".to_string(),
-            "argA".to_string(),
+    fn test_parse_end_of_options() {
+        let cli = cli(&["-j", "--", "-r", "--json", "1"]);
+        assert!(cli.flag(&JSON));
+        assert!(!cli.flag(&REVERSE));
+        assert_eq!(cli.args, vec!["tsp", "-r", "--json", "1"]);
+    }
+
+    #[test]
+    fn test_parse_negative_values() {
+        let cli = cli(&["-86400", "-", "-m1000", "m-1000", "--ref", "-1", "-j"]);
+        assert_eq!(cli.args, vec!["tsp", "-86400", "-", "-m1000", "m-1000"]);
+        assert!(cli.flag(&JSON));
+    }
+
+    #[test]
+    fn test_parse_command() {
+        let cli = cli(&["-j", "diff", "--pairs", "1", "diff"]);
+        assert!(get_diff(&cli));
+        assert!(get_pairs(&cli));
+        assert_eq!(cli.args, vec!["tsp", "1", "diff"]);
+
+        assert!(!get_diff(&self::cli(&["1", "diff"])));
+        assert!(!get_diff(&self::cli(&["--", "diff", "1"])));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let unknown = |x: &str| ArgsError::InvalidOption(format!("unknown option: '{x}'"));
+        assert_eq!(parse_err(&["--rubbish"]), unknown("--rubbish"));
+        assert_eq!(parse_err(&["--rubbish=1"]), unknown("--rubbish"));
+        assert_eq!(parse_err(&["-jx"]), unknown("-x"));
+        assert_eq!(
+            parse_err(&["argA", "-T"]),
+            ArgsError::InvalidOption("missing value for option: '-T'".to_string())
+        );
+        assert_eq!(
+            parse_err(&["--format"]),
+            ArgsError::InvalidOption("missing value for option: '--format'".to_string())
+        );
+        assert_eq!(
+            parse_err(&["--json=yes"]),
+            ArgsError::InvalidOption("unexpected value for option: '--json'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_help() {
+        assert!(get_help(&cli(&["1758642010", "--help"])));
+        assert!(get_help(&cli(&["1758642010", "-h"])));
+        assert!(!get_help(&cli(&["1758642010"])));
+        assert!(!get_help(&cli(&["--", "-h"])));
+    }
+
+    #[test]
+    fn test_get_sink() {
+        // can't test the value returned by `get_sink`, as it's an opaque sink
+        assert!(get_sink::<Outcome>(&cli(&["argA"])).is_ok());
+        assert!(get_sink::<Outcome>(&cli(&["--output", "xml"])).is_err());
+    }
+
+    #[test]
+    fn test_get_formatter() {
+        assert_eq!(get_formatter(&cli(&["argA"])).unwrap().name, "text");
+        assert_eq!(get_formatter(&cli(&["-j"])).unwrap().name, "json");
+        assert_eq!(get_formatter(&cli(&["--json"])).unwrap().name, "json");
+        let got = get_formatter(&cli(&["-j", "--output", "csv", "argA"]));
+        assert_eq!(got.unwrap().name, "csv");
+    }
+
+    #[test]
+    fn test_get_formatter_config() {
+        let config = || env_config(&[("TSP_OUTPUT", "csv")]);
+        let got = get_formatter(&cli(&["argA"]).with_config(config()));
+        assert_eq!(got.unwrap().name, "csv");
+        let got = get_formatter(&cli(&["-j"]).with_config(config()));
+        assert_eq!(got.unwrap().name, "json");
+        let got = get_formatter(&cli(&["--output", "yaml"]).with_config(config()));
+        assert_eq!(got.unwrap().name, "yaml");
+    }
+
+    #[test]
+    fn test_get_formatter_unknown() {
+        let error = get_formatter(&cli(&["--output", "xml"])).unwrap_err();
+        assert_eq!(
+            error,
+            ArgsError::UnknownOutput(
+                "unknown output format: 'xml', expected one of text, json, ndjson, csv, tsv, yaml, markdown"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_get_strict() {
+        assert!(get_strict(&cli(&["--strict", "argA"])));
+        assert!(!get_strict(&cli(&["argA"])));
+    }
+
+    #[test]
+    fn test_get_files() {
+        let cli = cli(&["-f", "a.log", "argA", "--file", "-"]);
+        assert_eq!(get_files(&cli), vec!["a.log".to_string(), "-".to_string()]);
+        assert_eq!(cli.args, vec!["tsp", "argA"]);
+    }
+
+    #[test]
+    fn test_get_filter() {
+        let with = cli(&["--filter", "--annotate", "argA"]);
+        assert!(get_filter(&with));
+        assert!(get_annotate(&with));
+        let without = cli(&["argA"]);
+        assert!(!get_filter(&without));
+        assert!(!get_annotate(&without));
+    }
+
+    #[test]
+    fn test_get_auto() {
+        assert!(get_auto(&cli(&["argA", "--auto"])));
+    }
+
+    #[test]
+    fn test_get_reverse() {
+        let cli = cli(&["-r", "-U", "m", "argA"]);
+        assert!(get_reverse(&cli));
+        assert_eq!(get_unit(&cli), Unit::Millis);
+        assert_eq!(cli.args, vec!["tsp", "argA"]);
+    }
+
+    #[test]
+    fn test_get_unit_default() {
+        for unit in ["a", "x"] {
+            assert_eq!(get_unit(&cli(&["--unit", unit])), Unit::Secs);
+        }
+        assert_eq!(get_unit(&cli(&[])), Unit::Secs);
+    }
+
+    #[test]
+    fn test_get_relative() {
+        let opts = ValueOptions::default();
+        let utc = Zone::from(UTC);
+        assert!(get_relative(&cli(&["argA"]), &opts, &utc).is_none());
+
+        let cli = |reference| cli(&["--relative-full", "--ref", reference, "argA"]);
+        let relative = get_relative(&cli("m1758643530000"), &opts, &utc).unwrap();
+        let dt = chrono::DateTime::from_timestamp(1758643590, 0).unwrap();
+        assert_eq!(relative.describe(&dt), "1m after");
+
+        let relative = get_relative(&cli("1758643530+2m"), &opts, &utc).unwrap();
+        assert_eq!(relative.describe(&dt), "1m before");
+    }
+
+    #[test]
+    fn test_get_relative_invalid_ref() {
+        let cli = cli(&["--ref", "rubbish"]);
+        let relative = get_relative(&cli, &ValueOptions::default(), &UTC.into()).unwrap();
+        assert_eq!(relative.describe(&chrono::Utc::now()), "now");
+    }
+
+    #[test]
+    fn test_get_select() {
+        let opts = ValueOptions::default();
+        let utc = Zone::from(UTC);
+        assert_eq!(
+            get_select(&cli(&["argA"]), &opts, &utc),
+            Ok(Select::default())
+        );
+
+        let cli = cli(&[
+            "--sort",
+            "--after",
+            "m1758643530000",
+            "--before=1758643530+1h",
+        ]);
+        let dt = |secs| chrono::DateTime::from_timestamp(secs, 0);
+        assert_eq!(
+            get_select(&cli, &opts, &utc),
+            Ok(Select {
+                after: dt(1758643530),
+                before: dt(1758647130),
+                sort: true,
+                dedupe: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_check_mode() {
+        assert_eq!(check_mode(&cli(&["--sort", "--stats", "argA"])), Ok(()));
+        assert_eq!(check_mode(&cli(&["diff", "argA", "argB"])), Ok(()));
+        let err = |msg: &str| Err(ArgsError::InvalidOption(msg.to_string()));
+        assert_eq!(
+            check_mode(&cli(&["diff", "--sort", "argA", "argB"])),
+            err("option '--sort' can't be used with diff")
+        );
+        assert_eq!(
+            check_mode(&cli(&["-r", "--after=0", "argA"])),
+            err("option '--after' can't be used with --reverse")
+        );
+        assert_eq!(
+            check_mode(&cli(&["--filter", "-r", "--delta"])),
+            err("option '--delta' can't be used with --filter")
+        );
+        assert_eq!(
+            check_mode(&cli(&["--annotate", "--stats"])),
+            err("option '--stats' can't be used with --annotate")
+        );
+        assert_eq!(
+            check_mode(&cli(&["diff", "--id", "argA", "argB"])),
+            err("option '--id' can't be used with diff")
+        );
+        assert_eq!(
+            check_mode(&cli(&["--filter", "--epoch", "discord"])),
+            err("option '--epoch' can't be used with --filter")
+        );
+    }
+
+    #[test]
+    fn test_check_mode_diff() {
+        let args = [
+            "diff",
+            "--pairs",
+            "--auto",
+            "--window=2000:2100",
+            "-j",
+            "--strict",
+        ];
+        assert_eq!(check_mode(&cli(&args)), Ok(()));
+        let err = |msg: &str| Err(ArgsError::InvalidOption(msg.to_string()));
+        for (opt, long) in [
+            ("--relative", "relative"),
+            ("--ref=now", "ref"),
+            ("-F%Y", "format"),
+            ("--filter", "filter"),
+            ("-r", "reverse"),
+            ("-Ums", "unit"),
+        ] {
+            assert_eq!(
+                check_mode(&cli(&["diff", opt, "argA", "argB"])),
+                err(&format!("option '--{long}' can't be used with diff"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_mode_filter() {
+        let args = ["--filter", "--annotate", "-F%Y", "--window=2000:2100"];
+        assert_eq!(check_mode(&cli(&args)), Ok(()));
+        let err = |msg: &str| Err(ArgsError::InvalidOption(msg.to_string()));
+        for (opt, long) in [
+            ("--relative-full", "relative-full"),
+            ("-j", "json"),
+            ("--output=csv", "output"),
+            ("--strict", "strict"),
+            ("--auto", "auto"),
+            ("-r", "reverse"),
+            ("--pairs", "pairs"),
+        ] {
+            assert_eq!(
+                check_mode(&cli(&["--filter", opt])),
+                err(&format!("option '--{long}' can't be used with --filter"))
+            );
+            assert_eq!(
+                check_mode(&cli(&["--annotate", opt])),
+                err(&format!("option '--{long}' can't be used with --annotate"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_mode_reverse() {
+        let args = ["-r", "-F%Y", "-Ums", "-j", "--strict"];
+        assert_eq!(check_mode(&cli(&args)), Ok(()));
+        let err = |msg: &str| Err(ArgsError::InvalidOption(msg.to_string()));
+        for (opt, long) in [
+            ("--relative", "relative"),
+            ("--ref=now", "ref"),
+            ("--auto", "auto"),
+            ("--window=2000:2100", "window"),
+            ("--pairs", "pairs"),
+        ] {
+            assert_eq!(
+                check_mode(&cli(&["-r", opt])),
+                err(&format!("option '--{long}' can't be used with --reverse"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_mode_conversions() {
+        let args = [
+            "--relative",
+            "--ref=now",
+            "--auto",
+            "--window=2000:2100",
+            "-j",
         ];
-        let fmt_str = get_fmt_str(&mut some_args);
-        assert_eq!(some_args, vec!["tsp".to_string(), "argA".to_string()]);
-        assert_eq!(fmt_str, "a-format".to_string());
+        assert_eq!(check_mode(&cli(&args)), Ok(()));
+        let err = |msg: &str| Err(ArgsError::InvalidOption(msg.to_string()));
+        assert_eq!(
+            check_mode(&cli(&["--pairs", "argA"])),
+            err("option '--pairs' can't be used with conversions")
+        );
+        assert_eq!(
+            check_mode(&cli(&["-Ums", "argA"])),
+            err("option '--unit' can't be used with conversions")
+        );
+    }
+
+    #[test]
+    fn test_get_select_invalid_bound() {
+        let cli = cli(&["--dedupe", "--before", "rubbish"]);
+        assert_eq!(
+            get_select(&cli, &ValueOptions::default(), &UTC.into()),
+            Err(ArgsError::InvalidOption(
+                "invalid value for option '--before': 'rubbish' (can't interpret the value)"
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_ids() {
+        let dt = |millis| chrono::DateTime::from_timestamp_millis(millis);
+        assert_eq!(get_ids(&cli(&["argA"])), Ok(None));
+        assert_eq!(get_ids(&cli(&["--id", "argA"])), Ok(dt(1288834974657)));
+        assert_eq!(
+            get_ids(&cli(&["--epoch", "discord", "argA"])),
+            Ok(dt(1420070400000))
+        );
+        assert_eq!(
+            get_ids(&cli(&["--id", "--epoch=m1704067200000", "argA"])),
+            Ok(dt(1704067200000))
+        );
+        assert_eq!(
+            get_ids(&cli(&["--epoch", "rubbish"])),
+            Err(ArgsError::InvalidOption(
+                "invalid value for option '--epoch': 'rubbish' (can't interpret the value)"
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_window() {
+        let window = get_window(&cli(&["--window", "1990..2050", "argA"]));
+        assert_eq!(window, "1990..2050".parse::<Window>().unwrap());
+    }
+
+    #[test]
+    fn test_get_window_default() {
+        assert_eq!(get_window(&cli(&["--window=rubbish"])), Window::default());
+    }
+
+    #[test]
+    fn test_get_fmt_str() {
+        let cli = cli(&["-F", "a-format", "argA"]);
+        assert_eq!(get_fmt_str(&cli).unwrap(), "a-format".to_string());
+        assert_eq!(cli.args, vec!["tsp", "argA"]);
     }
 
     #[test]
     fn test_get_fmt_str_default() {
-        let mut some_args: Vec<String> =
-            vec!["tsp".to_string(), "argA".to_string(), "-F".to_string()];
-        let fmt_str = get_fmt_str(&mut some_args);
-        assert_eq!(fmt_str, DEFAULT_DATE_FORMAT.to_string());
-        assert_eq!(some_args, vec!["tsp".to_string(), "argA".to_string(),]);
+        assert_eq!(
+            get_fmt_str(&cli(&["argA"])).unwrap(),
+            DEFAULT_DATE_FORMAT.to_string()
+        );
+    }
+
+    #[test]
+    fn test_get_fmt_str_config() {
+        let config = || env_config(&[("TSP_FORMAT", "%Y")]);
+        assert_eq!(
+            get_fmt_str(&cli(&["argA"]).with_config(config())).unwrap(),
+            "%Y"
+        );
+        let cli = cli(&["-F", "%d", "argA"]).with_config(config());
+        assert_eq!(get_fmt_str(&cli).unwrap(), "%d");
+    }
+
+    #[test]
+    fn test_get_fmt_str_preset() {
+        assert_eq!(
+            get_fmt_str(&cli(&["-F", "rfc3339"])).unwrap(),
+            "%Y-%m-%dT%H:%M:%S%:z"
+        );
+        assert_eq!(
+            get_fmt_str(&cli(&["-F", "iso8601"])).unwrap(),
+            "%Y-%m-%dT%H:%M:%S%:z"
+        );
+        let config = env_config(&[("TSP_FORMAT", "date")]);
+        assert_eq!(
+            get_fmt_str(&cli(&[]).with_config(config)).unwrap(),
+            "%Y-%m-%d"
+        );
+    }
+
+    #[test]
+    fn test_get_fmt_str_alias() {
+        let path = std::env::temp_dir().join("tsp_test_get_fmt_str_alias.toml");
+        let content = "[formats]\nshort = \"%H:%M\"\nsame = \"sql\"\ndate = \"%d/%m/%Y\"\n";
+        std::fs::write(&path, content).unwrap();
+        let config = || load_from(Some(path.to_owned()), |_| None).unwrap();
+        let got = |fmt: &str| get_fmt_str(&cli(&["-F", fmt]).with_config(config())).unwrap();
+        assert_eq!(got("short"), "%H:%M");
+        assert_eq!(got("same"), "%Y-%m-%d %H:%M:%S");
+        // aliases win over presets
+        assert_eq!(got("date"), "%d/%m/%Y");
+
+        let cli = cli(&["--list-formats"]).with_config(config());
+        std::fs::remove_file(path).unwrap();
+        assert!(get_list_formats(&cli));
+        let now = chrono::DateTime::from_timestamp(1758643530, 0).unwrap();
+        let listed = list_formats(&cli, &chrono_tz::Europe::Paris.into(), now);
+        let lines: Vec<&str> = listed.lines().collect();
+        assert_eq!(
+            lines[0],
+            "rfc2822 (rfc-2822)                %a, %d %b %Y %H:%M:%S %z   Tue, 23 Sep 2025 18:05:30 +0200"
+        );
+        assert_eq!(
+            lines[lines.len() - 1],
+            "short (config)                    %H:%M                      18:05"
+        );
+        assert_eq!(lines.len(), 11);
+    }
+
+    #[test]
+    fn test_show_config() {
+        let cli = cli(&["--show-config", "-T", "Asia/Tokyo", "-T", "UTC"])
+            .with_config(env_config(&[("TSP_FORMAT", "%Y-%m-%d \"%Z\"")]));
+        assert!(get_show_config(&cli));
+        assert_eq!(
+            show_config(&cli),
+            [
+                "# config file: none",
+                "format = \"%Y-%m-%d \\\"%Z\\\"\"  # environment",
+                "tz = \"Asia/Tokyo,UTC\"  # command line",
+                "output = \"text\"  # default",
+            ]
+            .join("\n")
+        );
     }
 
     #[test]
     fn test_validate_fmt_ok() {
         let fmt = "%Y%m%d".to_string();
-        let got = validate_fmt(fmt.to_owned());
-        assert_eq!(got, fmt);
+        let got = validate_fmt(fmt.to_owned(), true, check_fmt);
+        assert_eq!(got, Ok(fmt));
     }
 
     #[test]
     fn test_validate_fmt_err() {
         let fmt = "%N".to_string();
-        let got = validate_fmt(fmt.to_owned());
-        assert_eq!(got, DEFAULT_DATE_FORMAT);
+        let got = validate_fmt(fmt.to_owned(), false, check_fmt);
+        assert_eq!(got, Ok(DEFAULT_DATE_FORMAT.to_string()));
+    }
+
+    #[test]
+    fn test_validate_fmt_strict() {
+        let got = validate_fmt("%Y-%m-%Q".to_string(), true, check_fmt);
+        assert_eq!(
+            got,
+            Err(ArgsError::InvalidFormat(
+                "invalid format string: '%Y-%m-%Q': invalid specifier '%Q' at column 7".to_string()
+            ))
+        );
+        let got = get_fmt_str(&cli(&["--strict", "-F", "%Y-%Q"]));
+        assert!(got.is_err());
+    }
+
+    #[test]
+    fn test_get_fmt_str_parse_only() {
+        let got = get_fmt_str(&cli(&["--strict", "-F", "%H:%M %#z"]));
+        assert_eq!(
+            got,
+            Err(ArgsError::InvalidFormat(
+                "invalid format string: '%H:%M %#z': invalid specifier '%#z' at column 7"
+                    .to_string()
+            ))
+        );
+        let got = get_fmt_str(&cli(&["--strict", "-r", "-F", "%H:%M %#z"]));
+        assert_eq!(got, Ok("%H:%M %#z".to_string()));
     }
 
     #[test]
     fn test_get_tz() {
-        let mut some_args: Vec<String> = vec![
-            "tsp".to_string(),
-            "-T".to_string(),
-            "Europe/Paris".to_string(),
-            "argA".to_string(),
-        ];
-        let tz = get_tz(&mut some_args);
-        assert_eq!(some_args, vec!["tsp".to_string(), "argA".to_string()]);
-        assert_eq!(tz, chrono_tz::Europe::Paris);
+        let tzs = get_tzs(&cli(&["-T", "Europe/Paris", "argA"])).unwrap();
+        assert_eq!(tzs, vec![Zone::from(chrono_tz::Europe::Paris)]);
+    }
+
+    #[test]
+    fn test_get_tzs_multiple() {
+        let cli = cli(&[
+            "-T",
+            "Europe/Paris,America/New_York",
+            "argA",
+            "--tz=Asia/Tokyo",
+        ]);
+        assert_eq!(
+            get_tzs(&cli).unwrap(),
+            vec![
+                Zone::from(chrono_tz::Europe::Paris),
+                Zone::from(chrono_tz::America::New_York),
+                Zone::from(chrono_tz::Asia::Tokyo)
+            ]
+        );
     }
 
     #[test]
     fn test_get_fmt_tz_default() {
-        let mut some_args: Vec<String> = vec!["tsp".to_string(), "argA".to_string()];
-        let tz = get_tz(&mut some_args);
-        assert_eq!(tz, UTC);
-        assert_eq!(some_args, vec!["tsp".to_string(), "argA".to_string(),]);
+        let tzs = get_tzs(&cli(&["argA"])).unwrap();
+        assert_eq!(tzs, vec![Zone::from(UTC)]);
+    }
+
+    #[test]
+    fn test_get_tzs_config() {
+        let config = || env_config(&[("TSP_TZ", "Europe/Paris,Asia/Tokyo")]);
+        let tzs = get_tzs(&cli(&["argA"]).with_config(config())).unwrap();
+        assert_eq!(
+            tzs,
+            vec![
+                Zone::from(chrono_tz::Europe::Paris),
+                Zone::from(chrono_tz::Asia::Tokyo)
+            ]
+        );
+        let tzs = get_tzs(&cli(&["-T", "UTC"]).with_config(config())).unwrap();
+        assert_eq!(tzs, vec![Zone::from(UTC)]);
+    }
+
+    #[test]
+    fn test_get_tzs_utc_format() {
+        let tzs = get_tzs(&cli(&["-F", "http", "-T", "Europe/Paris,Asia/Tokyo"])).unwrap();
+        assert_eq!(tzs, vec![Zone::from(UTC)]);
+        let tzs = get_tzs(&cli(&["-F", "http-date", "-T", "Etc/UTC"])).unwrap();
+        assert_eq!(tzs, vec![Zone::from(chrono_tz::Etc::UTC)]);
+        let config = env_config(&[("TSP_FORMAT", "http"), ("TSP_TZ", "Europe/Paris")]);
+        let tzs = get_tzs(&cli(&["argA"]).with_config(config)).unwrap();
+        assert_eq!(tzs, vec![Zone::from(UTC)]);
+        let tzs = get_tzs(&cli(&["-F", "rfc2822", "-T", "Europe/Paris"])).unwrap();
+        assert_eq!(tzs, vec![Zone::from(chrono_tz::Europe::Paris)]);
     }
 
     #[test]
-    fn test_get_fmt_tz_missing_value() {
-        let mut some_args: Vec<String> =
-            vec!["tsp".to_string(), "argA".to_string(), "-T".to_string()];
-        let tz = get_tz(&mut some_args);
-        assert_eq!(tz, UTC);
-        assert_eq!(some_args, vec!["tsp".to_string(), "argA".to_string(),]);
+    fn test_get_tzs_unknown() {
+        let error = get_tzs(&cli(&["-T", "Europe/Paris,Europe/Pari"])).unwrap_err();
+        assert_eq!(
+            error,
+            ArgsError::UnknownTz(
+                "unknown timezone: 'Europe/Pari', did you mean Europe/Paris?".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_get_tzs_lenient() {
+        let tzs = get_tzs(&cli(&["-T", "Europe/Pari", "--lenient-tz"])).unwrap();
+        assert_eq!(tzs, vec![Zone::from(UTC)]);
     }
 
     #[test]
     fn test_validate_tz_ok() {
         let tz_str = "Europe/Paris".to_string();
-        let tz = validate_tz(tz_str.to_owned());
-        assert_eq!(tz, chrono_tz::Europe::Paris);
+        let tz = validate_tz(tz_str.to_owned(), false).unwrap();
+        assert_eq!(tz, Zone::from(chrono_tz::Europe::Paris));
     }
 
     #[test]
-    fn test_validate_tz_err() {
-        let tz_str = "Atlantis/Atlantis_City".to_string();
-        let tz = validate_tz(tz_str.to_owned());
-        assert_eq!(tz, chrono_tz::UTC);
+    fn test_split_tzs() {
+        assert_eq!(
+            split_tzs("Europe/Paris, EST5EDT,M3.2.0,M11.1.0,+05:30,MST7MDT,J60,300"),
+            vec![
+                "Europe/Paris",
+                "EST5EDT,M3.2.0,M11.1.0",
+                "+05:30",
+                "MST7MDT,J60,300"
+            ]
+        );
     }
 
     #[test]
-    fn test_no_cleanup() {
-        let mut some_args: Vec<String> =
-            vec!["tsp".to_string(), "argA".to_string(), "argB".to_string()];
-        let cleaned = cleanup(&mut some_args);
-        assert_eq!(cleaned, false);
+    fn test_validate_tz_specs() {
+        for tz_str in ["+05:30", "UTC-8", "local", "EST5EDT,M3.2.0,M11.1.0"] {
+            let tz = validate_tz(tz_str.to_string(), false).unwrap();
+            assert!(!matches!(tz, Zone::Named(_)) || tz_str == "local");
+        }
+    }
+
+    #[test]
+    fn test_validate_tz_err() {
+        let tz_str = "Atlantis/Atlantis_City".to_string();
+        let error = validate_tz(tz_str.to_owned(), false).unwrap_err();
         assert_eq!(
-            some_args,
-            vec!["tsp".to_string(), "argA".to_string(), "argB".to_string()]
+            error,
+            ArgsError::UnknownTz("unknown timezone: 'Atlantis/Atlantis_City'".to_string())
         );
     }
 
     #[test]
-    fn test_some_cleanup() {
-        let mut some_args: Vec<String> = vec![
-            "tsp".to_string(),
-            "argA".to_string(),
-            "-rubbish".to_string(),
-        ];
-        let cleaned = cleanup(&mut some_args);
-        assert_eq!(cleaned, true);
-        assert_eq!(some_args, vec!["tsp".to_string(), "argA".to_string()]);
+    fn test_validate_tz_err_lenient() {
+        let tz_str = "Atlantis/Atlantis_City".to_string();
+        let tz = validate_tz(tz_str.to_owned(), true).unwrap();
+        assert_eq!(tz, Zone::from(chrono_tz::UTC));
     }
 }
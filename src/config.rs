@@ -0,0 +1,232 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const DIR_NAME: &str = "tsp";
+const FILE_NAME: &str = "config.toml";
+const ENV_PREFIX: &str = "TSP_";
+
+#[derive(Debug, PartialEq)]
+pub struct ConfigError(String);
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+impl std::error::Error for ConfigError {}
+
+// Where a setting comes from, the command line taking precedence over the rest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Default,
+    File,
+    Env,
+    Cli,
+}
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Source::Default => "default",
+            Source::File => "config file",
+            Source::Env => "environment",
+            Source::Cli => "command line",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/*
+ * The settings of a layer, named after the long options they stand for,
+ * and the user's format aliases, only found in the file as a `[formats]` table.
+*/
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Settings {
+    format: Option<String>,
+    tz: Option<String>,
+    output: Option<String>,
+    formats: BTreeMap<String, String>,
+}
+impl Settings {
+    fn get(&self, key: &str) -> Option<&String> {
+        match key {
+            "format" => self.format.as_ref(),
+            "tz" => self.tz.as_ref(),
+            "output" => self.output.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/*
+ * Defaults read from `$XDG_CONFIG_HOME/tsp/config.toml` (`~/.config` when unset),
+ * overridden by the `TSP_FORMAT`, `TSP_TZ` and `TSP_OUTPUT` environment variables.
+ * A missing file is no error, an unreadable or invalid one is.
+*/
+#[derive(Debug, Default, PartialEq)]
+pub struct Config {
+    pub path: Option<PathBuf>,
+    file: Settings,
+    env: Settings,
+}
+impl Config {
+    pub fn get(&self, key: &str) -> Option<(String, Source)> {
+        match self.env.get(key) {
+            Some(value) => Some((value.to_owned(), Source::Env)),
+            None => self
+                .file
+                .get(key)
+                .map(|value| (value.to_owned(), Source::File)),
+        }
+    }
+
+    pub fn formats(&self) -> &BTreeMap<String, String> {
+        &self.file.formats
+    }
+}
+
+pub fn load() -> Result<Config, ConfigError> {
+    let var = |name: &str| env::var(name).ok();
+    load_from(config_path(var), var)
+}
+
+pub fn load_from(
+    path: Option<PathBuf>,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<Config, ConfigError> {
+    let file = match &path {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content).map_err(|err| {
+                let err = err.to_string();
+                ConfigError(format!(
+                    "invalid config file '{}': {}",
+                    path.display(),
+                    err.trim_end()
+                ))
+            })?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Settings::default(),
+            Err(err) => {
+                return Err(ConfigError(format!(
+                    "can't read config file '{}': {err}",
+                    path.display()
+                )))
+            }
+        },
+        None => Settings::default(),
+    };
+    // empty variables are unset ones
+    let env_var = |key: &str| {
+        var(&format!("{ENV_PREFIX}{}", key.to_uppercase())).filter(|value| !value.is_empty())
+    };
+    let env = Settings {
+        format: env_var("format"),
+        tz: env_var("tz"),
+        output: env_var("output"),
+        ..Default::default()
+    };
+    Ok(Config { path, file, env })
+}
+
+// Relative paths in XDG variables are invalid and ignored, as the spec says.
+fn config_path(var: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let absolute = |name: &str| {
+        var(name)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+    };
+    let config_dir = absolute("XDG_CONFIG_HOME")
+        .or_else(|| absolute("HOME").map(|home| home.join(".config")))?;
+    Some(config_dir.join(DIR_NAME).join(FILE_NAME))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::{config_path, load_from, Config, Source};
+    use std::path::PathBuf;
+
+    fn no_var(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn test_config_path() {
+        let var = |name: &str| match name {
+            "XDG_CONFIG_HOME" => Some("/xdg".to_string()),
+            "HOME" => Some("/home/me".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            config_path(var),
+            Some(PathBuf::from("/xdg/tsp/config.toml"))
+        );
+
+        let var = |name: &str| match name {
+            "XDG_CONFIG_HOME" => Some("relative".to_string()),
+            "HOME" => Some("/home/me".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            config_path(var),
+            Some(PathBuf::from("/home/me/.config/tsp/config.toml"))
+        );
+
+        assert_eq!(config_path(no_var), None);
+    }
+
+    #[test]
+    fn test_load_layers() {
+        let path = std::env::temp_dir().join("tsp_test_load_layers.toml");
+        let content =
+            "format = \"%Y-%m-%d\"\ntz = \"Europe/Paris\"\n[formats]\nshort = \"%H:%M\"\n";
+        std::fs::write(&path, content).unwrap();
+        let var = |name: &str| match name {
+            "TSP_TZ" => Some("Asia/Tokyo".to_string()),
+            "TSP_OUTPUT" => Some("".to_string()),
+            _ => None,
+        };
+        let config = load_from(Some(path.to_owned()), var).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            config.get("format"),
+            Some(("%Y-%m-%d".to_string(), Source::File))
+        );
+        assert_eq!(
+            config.get("tz"),
+            Some(("Asia/Tokyo".to_string(), Source::Env))
+        );
+        assert_eq!(config.get("output"), None);
+        assert_eq!(config.get("formats"), None);
+        assert_eq!(config.formats().get("short"), Some(&"%H:%M".to_string()));
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let path = std::env::temp_dir().join("tsp_test_load_missing_file.toml");
+        let config = load_from(Some(path.to_owned()), no_var).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                path: Some(path),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_load_invalid_file() {
+        let path = std::env::temp_dir().join("tsp_test_load_invalid_file.toml");
+        for content in ["format = ", "formt = \"%Y\"", "tz = 1", "formats = 1"] {
+            std::fs::write(&path, content).unwrap();
+            let err = load_from(Some(path.to_owned()), no_var).unwrap_err();
+            assert!(
+                err.to_string().starts_with("invalid config file '"),
+                "{content}"
+            );
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::diff::{epoch_ns, human};
+
+// The time elapsed at a value, in nanoseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step {
+    pub since_previous: Option<i128>,
+    pub since_first: i128,
+    pub out_of_order: bool,
+}
+
+#[derive(Debug, PartialEq)]
+struct Gap {
+    nanos: i128,
+    from: String,
+    to: String,
+}
+
+// What's told at the end: the largest gap forward and the values out of order.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Summary {
+    pub largest_gap_ns: i128,
+    pub from: String,
+    pub to: String,
+    pub out_of_order: usize,
+}
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "largest gap: {}, from '{}' to '{}'",
+            human(self.largest_gap_ns),
+            self.from,
+            self.to
+        )?;
+        if self.out_of_order > 0 {
+            write!(f, ", {} out of order", self.out_of_order)?;
+        }
+        Ok(())
+    }
+}
+
+/*
+ * Elapsed times along a sequence of values, from the previous valid one and
+ * from the first one: a value before the previous one is out of order.
+ * The largest gap forward is kept for the summary.
+*/
+#[derive(Debug, Default)]
+pub struct Sequence {
+    first: Option<DateTime<Utc>>,
+    previous: Option<(String, DateTime<Utc>)>,
+    largest: Option<Gap>,
+    out_of_order: usize,
+}
+impl Sequence {
+    pub fn step(&mut self, value: &str, dt: &DateTime<Utc>) -> Step {
+        let first = *self.first.get_or_insert(*dt);
+        let since_previous = self
+            .previous
+            .as_ref()
+            .map(|(_, previous)| epoch_ns(dt) - epoch_ns(previous));
+        let out_of_order = since_previous.is_some_and(|nanos| nanos < 0);
+        if out_of_order {
+            self.out_of_order += 1;
+        }
+        let largest = self.largest.as_ref().map_or(0, |gap| gap.nanos);
+        let larger = since_previous.filter(|nanos| *nanos > largest);
+        if let (Some(nanos), Some((from, _))) = (larger, &self.previous) {
+            self.largest = Some(Gap {
+                nanos,
+                from: from.to_owned(),
+                to: value.to_string(),
+            });
+        }
+        self.previous = Some((value.to_string(), *dt));
+        Step {
+            since_previous,
+            since_first: epoch_ns(dt) - epoch_ns(&first),
+            out_of_order,
+        }
+    }
+
+    // Nothing to tell without two valid values.
+    pub fn summary(&self) -> Option<Summary> {
+        let gap = self.largest.as_ref()?;
+        Some(Summary {
+            largest_gap_ns: gap.nanos,
+            from: gap.from.to_owned(),
+            to: gap.to.to_owned(),
+            out_of_order: self.out_of_order,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::delta::{Sequence, Step};
+
+    use chrono::DateTime;
+
+    #[test]
+    fn test_step() {
+        let mut sequence = Sequence::default();
+        let steps: Vec<Step> = [("a", 100), ("b", 160), ("c", 130), ("d", 400)]
+            .iter()
+            .map(|(value, secs)| sequence.step(value, &DateTime::from_timestamp(*secs, 0).unwrap()))
+            .collect();
+        let step = |since_previous: Option<i128>, since_first: i128, out_of_order| Step {
+            since_previous: since_previous.map(|x| x * 1_000_000_000),
+            since_first: since_first * 1_000_000_000,
+            out_of_order,
+        };
+        assert_eq!(
+            steps,
+            vec![
+                step(None, 0, false),
+                step(Some(60), 60, false),
+                step(Some(-30), 30, true),
+                step(Some(270), 300, false),
+            ]
+        );
+        let summary = sequence.summary().unwrap();
+        assert_eq!(
+            summary.to_string(),
+            "largest gap: 4m 30s, from 'c' to 'd', 1 out of order"
+        );
+        assert_eq!(
+            serde_json::to_value(&summary).unwrap(),
+            serde_json::json!({
+                "largest_gap_ns": 270_000_000_000_i64,
+                "from": "c",
+                "to": "d",
+                "out_of_order": 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_summary_without_gap() {
+        let mut sequence = Sequence::default();
+        assert_eq!(sequence.summary(), None);
+        sequence.step("a", &DateTime::from_timestamp(100, 0).unwrap());
+        assert_eq!(sequence.summary(), None);
+    }
+}
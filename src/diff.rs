@@ -0,0 +1,362 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::io;
+
+use crate::dump::{Record, Sink};
+use crate::expr;
+use crate::outcome::OutcomeStatus;
+use crate::status::Status;
+use crate::value::ValueOptions;
+use crate::zone::Zone;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+const UNITS: &[(u128, &str)] = &[(86400, "d"), (3600, "h"), (60, "m")];
+const MISSING_VALUE: &str = "missing_value";
+
+// Columns of tabular outputs, in the order of `Span::row`.
+pub const COLUMNS: &[&str] = &[
+    "from", "to", "duration", "status", "error", "iso", "seconds", "millis", "nanos",
+];
+
+/*
+ * The signed duration from one value to another, negative when `to` comes first.
+ * `duration` is what text output shows, in days, hours, minutes and seconds,
+ * or an error message; `iso` is an ISO 8601 duration counted in hours, days
+ * not being exact. `millis` is truncated toward zero.
+*/
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Span {
+    from: String,
+    to: String,
+    duration: String,
+    status: OutcomeStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iso: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seconds: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    millis: Option<i128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nanos: Option<i128>,
+}
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{0:20} -> {1:20} :: {2}",
+            self.from, self.to, self.duration
+        )
+    }
+}
+impl Record for Span {
+    const COLUMNS: &'static [&'static str] = COLUMNS;
+
+    fn row(&self) -> Vec<String> {
+        let opt = |x: Option<String>| x.unwrap_or_default();
+        vec![
+            self.from.to_owned(),
+            self.to.to_owned(),
+            self.duration.to_owned(),
+            self.status.to_string(),
+            self.error.unwrap_or_default().to_string(),
+            opt(self.iso.to_owned()),
+            opt(self.seconds.map(|x| x.to_string())),
+            opt(self.millis.map(|x| x.to_string())),
+            opt(self.nanos.map(|x| x.to_string())),
+        ]
+    }
+}
+impl Span {
+    pub fn new(from: &str, to: &str, start: &DateTime<Utc>, end: &DateTime<Utc>) -> Span {
+        let nanos = epoch_ns(end) - epoch_ns(start);
+        Span {
+            from: from.to_string(),
+            to: to.to_string(),
+            duration: human(nanos),
+            status: OutcomeStatus::Ok,
+            error: None,
+            iso: Some(iso(nanos)),
+            seconds: Some(nanos as f64 / NANOS_PER_SEC as f64),
+            millis: Some(nanos / 1_000_000),
+            nanos: Some(nanos),
+        }
+    }
+
+    fn failed(from: &str, to: &str, message: String, code: &'static str) -> Span {
+        Span {
+            from: from.to_string(),
+            to: to.to_string(),
+            duration: message,
+            status: OutcomeStatus::Error,
+            error: Some(code),
+            iso: None,
+            seconds: None,
+            millis: None,
+            nanos: None,
+        }
+    }
+}
+
+pub fn epoch_ns(dt: &DateTime<Utc>) -> i128 {
+    dt.timestamp() as i128 * NANOS_PER_SEC as i128 + dt.timestamp_subsec_nanos() as i128
+}
+
+// `.5`, the fraction of a second without trailing zeros, empty when there's none.
+fn fraction(nanos: u128) -> String {
+    match nanos {
+        0 => String::new(),
+        _ => format!(".{nanos:09}").trim_end_matches('0').to_string(),
+    }
+}
+
+// `-1d 2h 3.5s`, zero units left out.
+pub fn human(nanos: i128) -> String {
+    let sign = if nanos < 0 { "-" } else { "" };
+    let mut secs = nanos.unsigned_abs() / NANOS_PER_SEC;
+    let frac = nanos.unsigned_abs() % NANOS_PER_SEC;
+    let mut parts: Vec<String> = Vec::new();
+    for (size, short) in UNITS.iter() {
+        let count = secs / size;
+        secs %= size;
+        if count > 0 {
+            parts.push(format!("{count}{short}"));
+        }
+    }
+    if secs > 0 || frac > 0 || parts.is_empty() {
+        parts.push(format!("{secs}{}s", fraction(frac)));
+    }
+    format!("{sign}{}", parts.join(" "))
+}
+
+// `-PT26H2M3.5S`, `PT0S` when there's no difference.
+fn iso(nanos: i128) -> String {
+    let sign = if nanos < 0 { "-" } else { "" };
+    let secs = nanos.unsigned_abs() / NANOS_PER_SEC;
+    let frac = nanos.unsigned_abs() % NANOS_PER_SEC;
+    let (hours, minutes, secs) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    let mut out = format!("{sign}PT");
+    if hours > 0 {
+        out.push_str(&format!("{hours}H"));
+    }
+    if minutes > 0 {
+        out.push_str(&format!("{minutes}M"));
+    }
+    if secs > 0 || frac > 0 || (hours == 0 && minutes == 0) {
+        out.push_str(&format!("{secs}{}S", fraction(frac)));
+    }
+    out
+}
+
+/*
+ * Durations between consecutive values, or between the values of each pair.
+ * An invalid value gives a failed span, consecutive durations going on from
+ * the last valid one; a pair left without its second value fails too.
+*/
+pub fn go(
+    values: impl Iterator<Item = String>,
+    tz: Zone,
+    opts: ValueOptions,
+    pairs: bool,
+    strict: bool,
+    sink: &mut dyn Sink<Span>,
+) -> io::Result<Status> {
+    let mut values = values;
+    let head: Vec<String> = values.by_ref().take(2).collect();
+    if head.len() < 2 {
+        eprintln!("diff expects at least two values");
+        return Ok(Status::Usage);
+    }
+    let mut values = head.into_iter().chain(values);
+    let eval = |value: &str| expr::eval(value, &opts, &tz).map(|parsed| parsed.dt);
+    let mut status = Status::Converted;
+    let mut previous: Option<(String, DateTime<Utc>)> = None;
+    sink.begin()?;
+    while let Some(value) = values.next() {
+        let (from, to) = if pairs {
+            let Some(to) = values.next() else {
+                let message = "the pair has no second value".to_string();
+                sink.emit(&Span::failed(&value, "", message, MISSING_VALUE))?;
+                status = Status::Failed;
+                break;
+            };
+            (Some(value), to)
+        } else {
+            (previous.as_ref().map(|(value, _)| value.to_owned()), value)
+        };
+        let start = match (&from, &previous) {
+            (Some(from), _) if pairs => eval(from).map_err(|err| (from, err)),
+            (_, Some((_, start))) => Ok(*start),
+            // the first of consecutive values
+            (_, None) => match eval(&to) {
+                Ok(end) => {
+                    previous = Some((to, end));
+                    continue;
+                }
+                Err(err) => Err((&to, err)),
+            },
+        };
+        let end = start.and_then(|start| Ok((start, eval(&to).map_err(|err| (&to, err))?)));
+        match end {
+            Ok((start, end)) => {
+                let from = from.as_deref().unwrap_or_default();
+                sink.emit(&Span::new(from, &to, &start, &end))?;
+                if !pairs {
+                    previous = Some((to.to_owned(), end));
+                }
+            }
+            Err((value, err)) if strict => {
+                sink.finish()?;
+                eprintln!("invalid value '{value}': {err}");
+                return Ok(Status::Failed);
+            }
+            Err((_, err)) => {
+                let from = from.as_deref().unwrap_or_default();
+                sink.emit(&Span::failed(from, &to, err.to_string(), err.code()))?;
+                status = Status::Failed;
+            }
+        }
+    }
+    sink.finish()?;
+    Ok(status)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diff::{go, human, iso, Span};
+    use crate::dump::Record;
+    use crate::outcome::OutcomeStatus;
+    use crate::status::Status;
+    use crate::value::ValueOptions;
+
+    use chrono::DateTime;
+    use chrono_tz::UTC;
+
+    fn diff(values: &[&str], pairs: bool, strict: bool) -> (Status, Vec<Span>) {
+        let mut collected: Vec<Span> = Vec::new();
+        let values = values.iter().map(|x| x.to_string());
+        let opts = ValueOptions::default();
+        let status = go(values, UTC.into(), opts, pairs, strict, &mut collected).unwrap();
+        (status, collected)
+    }
+
+    fn durations(spans: &[Span]) -> Vec<(String, String, String)> {
+        spans
+            .iter()
+            .map(|x| (x.from.to_owned(), x.to.to_owned(), x.duration.to_owned()))
+            .collect()
+    }
+
+    fn expected(rows: &[(&str, &str, &str)]) -> Vec<(String, String, String)> {
+        rows.iter()
+            .map(|(a, b, c)| (a.to_string(), b.to_string(), c.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_human() {
+        assert_eq!(human(0), "0s");
+        assert_eq!(human(3_723_500_000_000), "1h 2m 3.5s");
+        assert_eq!(human(-3_723_500_000_000), "-1h 2m 3.5s");
+        assert_eq!(human(90_000_000_000_000), "1d 1h");
+        assert_eq!(human(1), "0.000000001s");
+    }
+
+    #[test]
+    fn test_iso() {
+        assert_eq!(iso(0), "PT0S");
+        assert_eq!(iso(3_723_500_000_000), "PT1H2M3.5S");
+        assert_eq!(iso(-3_723_500_000_000), "-PT1H2M3.5S");
+        assert_eq!(iso(93_600_000_000_000), "PT26H");
+        assert_eq!(iso(-250_000_000), "-PT0.25S");
+    }
+
+    #[test]
+    fn test_span() {
+        let start = DateTime::from_timestamp(1758643530, 0).unwrap();
+        let end = DateTime::from_timestamp(1758643529, 250_000_000).unwrap();
+        let span = Span::new("1758643530", "m1758643529250", &start, &end);
+        assert_eq!(span.status, OutcomeStatus::Ok);
+        assert_eq!(span.nanos, Some(-750_000_000));
+        assert_eq!(
+            span.row(),
+            vec![
+                "1758643530",
+                "m1758643529250",
+                "-0.75s",
+                "ok",
+                "",
+                "-PT0.75S",
+                "-0.75",
+                "-750",
+                "-750000000"
+            ]
+        );
+        assert_eq!(
+            span.to_string(),
+            "1758643530           -> m1758643529250       :: -0.75s"
+        );
+    }
+
+    #[test]
+    fn test_go_consecutive() {
+        let (status, spans) = diff(
+            &["1758643530", "m1758643531500", "1758643530"],
+            false,
+            false,
+        );
+        assert_eq!(status, Status::Converted);
+        assert_eq!(
+            durations(&spans),
+            expected(&[
+                ("1758643530", "m1758643531500", "1.5s"),
+                ("m1758643531500", "1758643530", "-1.5s"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_go_consecutive_errors() {
+        let (status, spans) = diff(&["nope", "1", "no", "3"], false, false);
+        assert_eq!(status, Status::Failed);
+        assert_eq!(
+            durations(&spans),
+            expected(&[
+                ("", "nope", "the value is not an integer"),
+                ("1", "no", "the value is not an integer"),
+                ("1", "3", "2s"),
+            ])
+        );
+        assert_eq!(spans[0].error, Some("not_an_integer"));
+    }
+
+    #[test]
+    fn test_go_pairs() {
+        let (status, spans) = diff(&["1", "61", "now", "nope", "5"], true, false);
+        assert_eq!(status, Status::Failed);
+        assert_eq!(
+            durations(&spans),
+            expected(&[
+                ("1", "61", "1m"),
+                ("now", "nope", "the value is not an integer"),
+                ("5", "", "the pair has no second value"),
+            ])
+        );
+        assert_eq!(spans[2].error, Some("missing_value"));
+    }
+
+    #[test]
+    fn test_go_strict() {
+        let (status, spans) = diff(&["1", "2", "nope", "3"], false, true);
+        assert_eq!(status, Status::Failed);
+        assert_eq!(durations(&spans), expected(&[("1", "2", "1s")]));
+    }
+
+    #[test]
+    fn test_go_not_enough() {
+        let (status, spans) = diff(&["1"], false, false);
+        assert_eq!(status, Status::Usage);
+        assert!(spans.is_empty());
+    }
+}
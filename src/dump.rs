@@ -1,63 +1,579 @@
+use std::io::{self, Write};
+
+use serde::Serialize;
+use serde_json::Value;
+
 use crate::outcome::Outcome;
 
-pub type DumpOutcomeFn = fn(Vec<Outcome>);
+/*
+ * What sinks write, outcomes of conversions or durations: text output is the
+ * `Display` rendering, tabular outputs the `row` of fields under `COLUMNS`,
+ * the others the serialization.
+*/
+pub trait Record: Serialize + std::fmt::Display {
+    const COLUMNS: &'static [&'static str];
+
+    fn row(&self) -> Vec<String>;
+}
+
+/*
+ * Where records go, one at a time: `begin` before the first one,
+ * `emit` for each of them and `finish` after the last one.
+ * All sinks but the Markdown table write as they go, with bounded memory.
+ * A `summary` may come before `finish`, as text and as a JSON value: JSON and
+ * YAML lists end with it, outputs that can't hold it leave it to stderr.
+*/
+pub trait Sink<R: Record = Outcome> {
+    fn begin(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn emit(&mut self, record: &R) -> io::Result<()>;
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn summary(&mut self, text: &str, _value: &Value) -> io::Result<()> {
+        eprintln!("{text}");
+        Ok(())
+    }
+}
+
+// Records collected in memory, for library users and tests.
+impl<R: Record + Clone> Sink<R> for Vec<R> {
+    fn emit(&mut self, record: &R) -> io::Result<()> {
+        self.push(record.to_owned());
+        Ok(())
+    }
+
+    fn summary(&mut self, _text: &str, _value: &Value) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Layout {
+    Text,
+    Json,
+    Ndjson,
+    Separated(char),
+    Yaml,
+    Markdown,
+}
+
+// An output format, selected by its name or one of its aliases.
+#[derive(Debug)]
+pub struct Formatter {
+    pub name: &'static str,
+    aliases: &'static [&'static str],
+    layout: Layout,
+}
+impl Formatter {
+    pub fn sink<R: Record>(&self, out: Box<dyn Write>) -> Box<dyn Sink<R>> {
+        match self.layout {
+            Layout::Text => Box::new(TextSink::new(out)),
+            Layout::Json => Box::new(JsonSink::new(out)),
+            Layout::Ndjson => Box::new(NdjsonSink::new(out)),
+            Layout::Separated(sep) => Box::new(SeparatedSink::new(out, sep)),
+            Layout::Yaml => Box::new(YamlSink::new(out)),
+            Layout::Markdown => Box::new(MarkdownSink::new(out)),
+        }
+    }
+}
+
+pub const FORMATTERS: &[Formatter] = &[
+    Formatter {
+        name: "text",
+        aliases: &[],
+        layout: Layout::Text,
+    },
+    Formatter {
+        name: "json",
+        aliases: &[],
+        layout: Layout::Json,
+    },
+    Formatter {
+        name: "ndjson",
+        aliases: &["jsonl"],
+        layout: Layout::Ndjson,
+    },
+    Formatter {
+        name: "csv",
+        aliases: &[],
+        layout: Layout::Separated(','),
+    },
+    Formatter {
+        name: "tsv",
+        aliases: &[],
+        layout: Layout::Separated('\t'),
+    },
+    Formatter {
+        name: "yaml",
+        aliases: &["yml"],
+        layout: Layout::Yaml,
+    },
+    Formatter {
+        name: "markdown",
+        aliases: &["md", "table"],
+        layout: Layout::Markdown,
+    },
+];
 
-fn text_dump(outcomes: Vec<Outcome>) {
-    for outcome in outcomes.iter() {
-        println!("{outcome}")
+pub fn get_formatter(name: &str) -> Option<&'static Formatter> {
+    let name = name.to_lowercase();
+    FORMATTERS
+        .iter()
+        .find(|x| x.name == name || x.aliases.contains(&name.as_str()))
+}
+
+struct TextSink<W: Write> {
+    out: W,
+}
+impl<W: Write> TextSink<W> {
+    fn new(out: W) -> TextSink<W> {
+        TextSink { out }
     }
 }
+impl<W: Write, R: Record> Sink<R> for TextSink<W> {
+    fn emit(&mut self, record: &R) -> io::Result<()> {
+        writeln!(self.out, "{record}")
+    }
 
-fn json_dump(outcomes: Vec<Outcome>) {
-    let json_pretty = serde_json::to_string_pretty(&outcomes).unwrap();
-    println!("{json_pretty}");
+    fn summary(&mut self, text: &str, _value: &Value) -> io::Result<()> {
+        writeln!(self.out, "{text}")
+    }
+}
+
+// The pretty printed array, written item by item.
+struct JsonSink<W: Write> {
+    out: W,
+    count: usize,
+}
+impl<W: Write> JsonSink<W> {
+    fn new(out: W) -> JsonSink<W> {
+        JsonSink { out, count: 0 }
+    }
+}
+impl<W: Write> JsonSink<W> {
+    fn item(&mut self, item: &impl Serialize) -> io::Result<()> {
+        let sep = if self.count == 0 { "[" } else { "," };
+        let json_pretty = serde_json::to_string_pretty(item)?;
+        writeln!(self.out, "{sep}")?;
+        write!(self.out, "  {}", json_pretty.replace('\n', "\n  "))?;
+        self.count += 1;
+        Ok(())
+    }
+}
+impl<W: Write, R: Record> Sink<R> for JsonSink<W> {
+    fn emit(&mut self, record: &R) -> io::Result<()> {
+        self.item(record)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if self.count == 0 {
+            writeln!(self.out, "[]")
+        } else {
+            writeln!(self.out, "\n]")
+        }
+    }
+
+    // The last item of the array, a single JSON document.
+    fn summary(&mut self, _text: &str, value: &Value) -> io::Result<()> {
+        self.item(value)
+    }
+}
+
+struct NdjsonSink<W: Write> {
+    out: W,
+}
+impl<W: Write> NdjsonSink<W> {
+    fn new(out: W) -> NdjsonSink<W> {
+        NdjsonSink { out }
+    }
+}
+impl<W: Write, R: Record> Sink<R> for NdjsonSink<W> {
+    fn emit(&mut self, record: &R) -> io::Result<()> {
+        writeln!(self.out, "{}", serde_json::to_string(record)?)
+    }
+
+    fn summary(&mut self, _text: &str, value: &Value) -> io::Result<()> {
+        writeln!(self.out, "{}", serde_json::to_string(value)?)
+    }
+}
+
+struct SeparatedSink<W: Write> {
+    out: W,
+    sep: char,
+}
+impl<W: Write> SeparatedSink<W> {
+    fn new(out: W, sep: char) -> SeparatedSink<W> {
+        SeparatedSink { out, sep }
+    }
+
+    fn line(&mut self, fields: &[String]) -> io::Result<()> {
+        let fields: Vec<String> = fields.iter().map(|x| separated(x, self.sep)).collect();
+        writeln!(self.out, "{}", fields.join(&self.sep.to_string()))
+    }
+}
+impl<W: Write, R: Record> Sink<R> for SeparatedSink<W> {
+    fn begin(&mut self) -> io::Result<()> {
+        let header: Vec<String> = R::COLUMNS.iter().map(|x| x.to_string()).collect();
+        self.line(&header)
+    }
+
+    fn emit(&mut self, record: &R) -> io::Result<()> {
+        self.line(&record.row())
+    }
 }
 
 /*
- * Could use an enum and bind the values to function.
- * Maybe if more formats are to be handled,
- * for now it just feels overkill.
+ * CSV fields are quoted as in RFC 4180 when they hold a separator, a quote or a line break.
+ * TSV fields can't be quoted, tabs, line breaks and backslashes are escaped instead.
 */
-pub fn get_fn(json: bool) -> DumpOutcomeFn {
-    if json {
-        return json_dump;
+fn separated(field: &str, sep: char) -> String {
+    if sep == '\t' {
+        field
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    } else if field.contains([sep, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
     }
-    text_dump
+}
+
+struct YamlSink<W: Write> {
+    out: W,
+    count: usize,
+}
+impl<W: Write> YamlSink<W> {
+    fn new(out: W) -> YamlSink<W> {
+        YamlSink { out, count: 0 }
+    }
+}
+impl<W: Write, R: Record> Sink<R> for YamlSink<W> {
+    fn emit(&mut self, record: &R) -> io::Result<()> {
+        self.count += 1;
+        writeln!(self.out, "{}", yaml(record)?)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if self.count == 0 {
+            writeln!(self.out, "[]")?;
+        }
+        Ok(())
+    }
+
+    // The last item of the list.
+    fn summary(&mut self, _text: &str, value: &Value) -> io::Result<()> {
+        self.count += 1;
+        writeln!(self.out, "{}", yaml(value)?)
+    }
+}
+
+// JSON scalars are valid YAML scalars, only the layout needs to be written.
+fn yaml(item: &impl Serialize) -> serde_json::Result<String> {
+    let value = yaml_value(&serde_json::to_value(item)?, 2);
+    Ok(value.replacen("\n  ", "- ", 1))
+}
+
+fn yaml_value(value: &Value, indent: usize) -> String {
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| {
+                let key = if key.chars().all(|c| c.is_ascii_lowercase() || c == '_') {
+                    key.to_string()
+                } else {
+                    Value::from(key.as_str()).to_string()
+                };
+                let value = yaml_value(value, indent + 2);
+                let sep = if value.starts_with('\n') { "" } else { " " };
+                format!("\n{:indent$}{key}:{sep}{value}", "")
+            })
+            .collect(),
+        _ => value.to_string(),
+    }
+}
+
+// Column widths depend on every row: the table can only be written at the end.
+struct MarkdownSink<W: Write> {
+    out: W,
+    rows: Vec<Vec<String>>,
+}
+impl<W: Write> MarkdownSink<W> {
+    fn new(out: W) -> MarkdownSink<W> {
+        MarkdownSink {
+            out,
+            rows: Vec::new(),
+        }
+    }
+}
+impl<W: Write, R: Record> Sink<R> for MarkdownSink<W> {
+    fn emit(&mut self, record: &R) -> io::Result<()> {
+        let row = record.row().into_iter().map(|x| x.replace('|', "\\|"));
+        self.rows.push(row.collect());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        write!(self.out, "{}", markdown(R::COLUMNS, &self.rows))
+    }
+}
+
+// An aligned table, without the columns that are empty on every row but the first three.
+fn markdown(columns: &[&str], rows: &[Vec<String>]) -> String {
+    let kept: Vec<usize> = (0..columns.len())
+        .filter(|&i| i < 3 || rows.iter().any(|row| !row[i].is_empty()))
+        .collect();
+    let widths: Vec<usize> = kept
+        .iter()
+        .map(|&i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([columns[i].len(), 3])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let line = |cells: Vec<String>| -> String {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        format!("| {} |\n", cells.join(" | "))
+    };
+    let mut out = line(kept.iter().map(|&i| columns[i].to_string()).collect());
+    out.push_str(&line(widths.iter().map(|&w| "-".repeat(w)).collect()));
+    for row in rows.iter() {
+        out.push_str(&line(kept.iter().map(|&i| row[i].to_owned()).collect()));
+    }
+    out
 }
 
 #[cfg(test)]
 mod test {
-    use crate::dump::get_fn;
-    use crate::dump::{json_dump, text_dump};
+    use crate::dump::get_formatter;
+    use crate::dump::{
+        JsonSink, MarkdownSink, NdjsonSink, SeparatedSink, Sink, TextSink, YamlSink,
+    };
+    use crate::outcome::Outcome;
+    use crate::unit::Unit;
+    use crate::value::ValueError;
+
+    use chrono::DateTime;
+    use serde_json::Value;
+
+    fn outcomes() -> Vec<Outcome> {
+        vec![
+            Outcome::new("1".to_string())
+                .set("Thu, 01 Jan 1970 00:00:01 +0000".to_string())
+                .set_unit(Unit::Secs, false)
+                .set_instant(&DateTime::from_timestamp(1, 0).unwrap()),
+            Outcome::new("a|b".to_string()).set_error(&ValueError::Nothing),
+        ]
+    }
+
+    fn run(sink: &mut dyn Sink, outcomes: &[Outcome]) {
+        sink.begin().unwrap();
+        for outcome in outcomes {
+            sink.emit(outcome).unwrap();
+        }
+        sink.finish().unwrap();
+    }
+
+    #[test]
+    fn test_get_formatter() {
+        assert_eq!(get_formatter("text").unwrap().name, "text");
+        assert_eq!(get_formatter("JSON").unwrap().name, "json");
+    }
+
+    #[test]
+    fn test_get_formatter_alias() {
+        assert_eq!(get_formatter("md").unwrap().name, "markdown");
+        assert_eq!(get_formatter("jsonl").unwrap().name, "ndjson");
+        assert!(get_formatter("xml").is_none());
+    }
+
+    #[test]
+    fn test_json() {
+        let mut sink = JsonSink::new(Vec::new());
+        run(&mut sink, &outcomes());
+        let expected = serde_json::to_string_pretty(&outcomes()).unwrap() + "\n";
+        assert_eq!(String::from_utf8(sink.out).unwrap(), expected);
+
+        let mut sink = JsonSink::new(Vec::new());
+        run(&mut sink, &[]);
+        assert_eq!(String::from_utf8(sink.out).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn test_csv() {
+        let mut sink = SeparatedSink::new(Vec::new(), ',');
+        run(&mut sink, &outcomes());
+        assert_eq!(
+            String::from_utf8(sink.out).unwrap(),
+            "value_in,value_out,status,error,unit,guessed,epoch_ns,instant,zones,relative,\
+             since_previous_ns,since_first_ns,out_of_order,id\n\
+             1,\"Thu, 01 Jan 1970 00:00:01 +0000\",ok,,secs,,1000000000,1970-01-01T00:00:01Z,,,,,,\n\
+             a|b,can't interpret the value,error,invalid_input,,,,,,,,,,\n"
+        );
+
+        let quoted = Outcome::new("say \"hi\"".to_string()).set("a\nb".to_string());
+        let mut sink = SeparatedSink::new(Vec::new(), ',');
+        run(&mut sink, &[quoted]);
+        let got = String::from_utf8(sink.out).unwrap();
+        assert!(got.ends_with("\"say \"\"hi\"\"\",\"a\nb\",ok,,,,,,,,,,,\n"));
+    }
 
     #[test]
-    fn test_get_fn_text() {
-        let dump_fn = get_fn(false);
-        assert!(std::ptr::addr_eq(
-            dump_fn as *const (),
-            text_dump as *const ()
-        ));
+    fn test_tsv() {
+        let escaped = Outcome::new("a\tb\\".to_string()).set("a\nb".to_string());
+        let mut sink = SeparatedSink::new(Vec::new(), '\t');
+        run(&mut sink, &[escaped]);
+        let got = String::from_utf8(sink.out).unwrap();
+        assert_eq!(
+            got.lines().nth(1).unwrap(),
+            "a\\tb\\\\\ta\\nb\tok\t\t\t\t\t\t\t\t\t\t\t"
+        );
+        assert_eq!(got.lines().next().unwrap().split('\t').count(), 14);
     }
 
     #[test]
-    fn test_get_fn_json() {
-        let dump_fn = get_fn(true);
-        assert!(std::ptr::addr_eq(
-            dump_fn as *const (),
-            json_dump as *const ()
-        ));
+    fn test_yaml() {
+        let zoned = Outcome::new("1".to_string()).set_zones(vec![
+            ("Europe/Paris".to_string(), "01:00".to_string()),
+            ("+05:30".to_string(), "05:30".to_string()),
+        ]);
+        let mut sink = YamlSink::new(Vec::new());
+        run(&mut sink, &[zoned, outcomes().remove(1)]);
+        assert_eq!(
+            String::from_utf8(sink.out).unwrap(),
+            "- value_in: \"1\"\n  value_out: \"01:00\"\n  status: \"ok\"\n  zones:\n    \
+             \"Europe/Paris\": \"01:00\"\n    \"+05:30\": \"05:30\"\n\
+             - value_in: \"a|b\"\n  value_out: \"can't interpret the value\"\n  \
+             status: \"error\"\n  error: \"invalid_input\"\n"
+        );
+
+        let mut sink = YamlSink::new(Vec::new());
+        run(&mut sink, &[]);
+        assert_eq!(String::from_utf8(sink.out).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn test_summary() {
+        let value = serde_json::json!({"stats": {"count": 2}});
+        let summarized = |sink: &mut dyn Sink, outcomes: &[Outcome]| {
+            sink.begin().unwrap();
+            for outcome in outcomes {
+                sink.emit(outcome).unwrap();
+            }
+            sink.summary("count: 2", &value).unwrap();
+            sink.finish().unwrap();
+        };
+
+        let mut sink = TextSink::new(Vec::new());
+        summarized(&mut sink, &outcomes()[..1]);
+        let got = String::from_utf8(sink.out).unwrap();
+        assert!(got.ends_with("+0000\ncount: 2\n"));
+
+        // the last item of a single document
+        let mut sink = JsonSink::new(Vec::new());
+        summarized(&mut sink, &outcomes()[..1]);
+        let parsed: Value = serde_json::from_slice(&sink.out).unwrap();
+        let items = parsed.as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["value_in"], "1");
+        assert_eq!(items[1]["stats"]["count"], 2);
+        let mut sink = JsonSink::new(Vec::new());
+        summarized(&mut sink, &[]);
+        let parsed: Value = serde_json::from_slice(&sink.out).unwrap();
+        assert_eq!(parsed, serde_json::json!([{"stats": {"count": 2}}]));
+
+        let mut sink = NdjsonSink::new(Vec::new());
+        summarized(&mut sink, &outcomes()[..1]);
+        let got = String::from_utf8(sink.out).unwrap();
+        assert!(got.ends_with("}\n{\"stats\":{\"count\":2}}\n"));
+
+        let mut sink = YamlSink::new(Vec::new());
+        summarized(&mut sink, &outcomes()[..1]);
+        let got = String::from_utf8(sink.out).unwrap();
+        assert!(got.ends_with("Z\"\n- stats:\n    count: 2\n"));
+        let mut sink = YamlSink::new(Vec::new());
+        summarized(&mut sink, &[]);
+        assert_eq!(
+            String::from_utf8(sink.out).unwrap(),
+            "- stats:\n    count: 2\n"
+        );
+    }
+
+    #[test]
+    fn test_json_stats() {
+        let mut sink = JsonSink::new(Vec::new());
+        let extras = crate::process::Extras {
+            stats: true,
+            ..Default::default()
+        };
+        let values = ["0", "nope", "60"].iter().map(|x| x.to_string());
+        let (fmt, tzs) = ("%H:%M".to_string(), vec![chrono_tz::UTC.into()]);
+        let opts = crate::value::ValueOptions::default();
+        crate::process::go(values, fmt, tzs, opts, extras, false, &mut sink).unwrap();
+
+        let parsed: Value = serde_json::from_slice(&sink.out).unwrap();
+        let items = parsed.as_array().unwrap();
+        assert_eq!(items.len(), 4);
+        let stats = &items[3]["stats"];
+        assert_eq!(stats["count"], 3);
+        assert_eq!(stats["converted"], 2);
+        assert_eq!(stats["failures"]["not_an_integer"], 1);
+        assert_eq!(stats["first"], "1970-01-01T00:00:00Z");
+        assert_eq!(stats["span_ns"], 60_000_000_000_i64);
+    }
+
+    #[test]
+    fn test_markdown() {
+        let mut sink = MarkdownSink::new(Vec::new());
+        run(&mut sink, &outcomes());
+        let got = String::from_utf8(sink.out).unwrap();
+        let lines: Vec<&str> = got.lines().collect();
+        assert_eq!(
+            lines[0],
+            "| value_in | value_out                       | status | error         | unit \
+             | epoch_ns   | instant              |"
+        );
+        assert!(lines[1].starts_with("| -------- | ------"));
+        assert_eq!(
+            lines[3],
+            "| a\\|b     | can't interpret the value       | error  | invalid_input |      \
+             |            |                      |"
+        );
+
+        let mut sink = MarkdownSink::new(Vec::new());
+        run(&mut sink, &[]);
+        assert_eq!(
+            String::from_utf8(sink.out).unwrap(),
+            "| value_in | value_out | status |\n| -------- | --------- | ------ |\n"
+        );
     }
 }
 
 #[cfg(test)]
 mod test_dummy {
-    use crate::dump::{json_dump, text_dump};
+    use crate::dump::FORMATTERS;
     use crate::outcome::Outcome;
 
     #[test]
     fn test_coverage_target() {
         let res = Outcome::new("test".to_string()).set("new".to_string());
-        text_dump(vec![res.clone()]);
-        json_dump(vec![res]);
+        for formatter in FORMATTERS {
+            let mut sink = formatter.sink::<Outcome>(Box::new(std::io::sink()));
+            sink.begin().unwrap();
+            sink.emit(&res).unwrap();
+            sink.finish().unwrap();
+        }
     }
 }
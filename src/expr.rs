@@ -0,0 +1,315 @@
+use chrono::{DateTime, Months, NaiveDateTime, Offset, TimeDelta, TimeZone, Utc};
+
+use crate::unit::Unit;
+use crate::value::{ts_from_str, Parsed, ValueError, ValueOptions};
+use crate::zone::Zone;
+
+const NOW: &str = "now";
+
+// Longer names first, `m` being minutes and `mo` months.
+const STEPS: &[(&str, Step)] = &[
+    ("mo", Step::Months(1)),
+    ("ms", Step::Nanos(1_000_000)),
+    ("us", Step::Nanos(1_000)),
+    ("µs", Step::Nanos(1_000)),
+    ("ns", Step::Nanos(1)),
+    ("y", Step::Months(12)),
+    ("w", Step::Days(7)),
+    ("d", Step::Days(1)),
+    ("h", Step::Nanos(3_600_000_000_000)),
+    ("m", Step::Nanos(60_000_000_000)),
+    ("s", Step::Nanos(1_000_000_000)),
+];
+
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Months(i64),
+    Days(i64),
+    Nanos(i128),
+}
+
+/*
+ * A duration like `1h30m`: years, months, weeks and days are calendar units,
+ * added to the local date, the others are exact.
+*/
+#[derive(Debug, Default, PartialEq)]
+struct Duration {
+    months: i64,
+    days: i64,
+    nanos: i128,
+}
+impl Duration {
+    fn parse(s: &str) -> Option<Duration> {
+        let mut duration = Duration::default();
+        let mut rest = s;
+        while !rest.is_empty() {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let count: i64 = rest[..digits].parse().ok()?;
+            rest = &rest[digits..];
+            let letters = rest
+                .find(|c: char| !c.is_alphabetic())
+                .unwrap_or(rest.len());
+            let (_, step) = STEPS.iter().find(|(name, _)| *name == &rest[..letters])?;
+            rest = &rest[letters..];
+            match step {
+                Step::Months(n) => {
+                    duration.months = duration.months.checked_add(count.checked_mul(*n)?)?
+                }
+                Step::Days(n) => {
+                    duration.days = duration.days.checked_add(count.checked_mul(*n)?)?
+                }
+                Step::Nanos(n) => duration.nanos = duration.nanos.checked_add(count as i128 * n)?,
+            }
+        }
+        (!s.is_empty()).then_some(duration)
+    }
+
+    fn add_to(&self, dt: DateTime<Utc>, tz: &Zone, negative: bool) -> Option<DateTime<Utc>> {
+        let sign = if negative { -1 } else { 1 };
+        let mut dt = dt;
+        if self.months != 0 || self.days != 0 {
+            let local = dt.with_timezone(tz);
+            let offset = local.offset().fix();
+            let naive = add_months(local.naive_local(), sign * self.months)?
+                .checked_add_signed(TimeDelta::try_days(sign * self.days)?)?;
+            // a local time skipped by a DST change keeps the offset it had before
+            dt = match tz.from_local_datetime(&naive).earliest() {
+                Some(dt) => dt.to_utc(),
+                None => (naive - offset).and_utc(),
+            };
+        }
+        let nanos = i64::try_from(sign as i128 * self.nanos).ok()?;
+        dt.checked_add_signed(TimeDelta::nanoseconds(nanos))
+    }
+}
+
+// Months added to the end of a shorter month stop at its last day.
+fn add_months(naive: NaiveDateTime, months: i64) -> Option<NaiveDateTime> {
+    let count = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+    if months < 0 {
+        naive.checked_sub_months(count)
+    } else {
+        naive.checked_add_months(count)
+    }
+}
+
+/*
+ * A value, or `now`, possibly followed by durations to add or subtract:
+ * `1758643530+1h30m`, `now-7d`, `m1758643530 - 250ms`.
+ * Calendar units are added in `tz`, which keeps the local time across DST changes.
+ * Anything else is left to `ts_from_str`.
+*/
+pub fn eval(s: &str, opts: &ValueOptions, tz: &Zone) -> Result<Parsed, ValueError> {
+    let (base, rest) = split_base(s);
+    let rest = rest.trim_start();
+    let is_expr = rest.starts_with(['+', '-']) || (base == NOW && rest.is_empty());
+    if !is_expr {
+        return ts_from_str(s.to_string(), opts);
+    }
+    let mut parsed = if base == NOW {
+        Parsed {
+            dt: opts.now,
+            unit: Unit::Secs,
+            guessed: false,
+            id: None,
+        }
+    } else {
+        ts_from_str(base.to_string(), opts)?
+    };
+    for (negative, duration) in terms(rest).ok_or(ValueError::NotAnExpr)? {
+        parsed.dt = duration
+            .add_to(parsed.dt, tz, negative)
+            .ok_or(ValueError::NotATS)?;
+    }
+    Ok(parsed)
+}
+
+// `now`, or a sign, a unit prefix, a sign and a number, none being required.
+fn split_base(s: &str) -> (&str, &str) {
+    if let Some(rest) = s.strip_prefix(NOW) {
+        return (NOW, rest);
+    }
+    let mut end = 0;
+    let mut chars = s.char_indices().peekable();
+    let mut skip = |pred: fn(char) -> bool, end: &mut usize| {
+        if let Some((idx, c)) = chars.next_if(|(_, c)| pred(*c)) {
+            *end = idx + c.len_utf8();
+        }
+    };
+    skip(|c| c == '-', &mut end);
+    skip(|c| c.is_ascii_alphabetic(), &mut end);
+    skip(|c| c == '-', &mut end);
+    while let Some((idx, c)) = chars.next_if(|(_, c)| c.is_ascii_digit() || *c == '.') {
+        end = idx + c.len_utf8();
+    }
+    s.split_at(end)
+}
+
+// `+ 1h30m -2d`: operators, each followed by a duration, spaces around being allowed.
+fn terms(s: &str) -> Option<Vec<(bool, Duration)>> {
+    let mut terms = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        let negative = match rest.chars().next() {
+            Some('+') => false,
+            Some('-') => true,
+            _ => return None,
+        };
+        rest = rest[1..].trim_start();
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '+' || c == '-')
+            .unwrap_or(rest.len());
+        terms.push((negative, Duration::parse(&rest[..end])?));
+        rest = rest[end..].trim_start();
+    }
+    Some(terms)
+}
+
+// Whether a word read after a value belongs to it, as in `m1758643530 - 250ms`.
+pub fn continues(value: &str, word: &str) -> bool {
+    let ends_with_op =
+        value.ends_with(['+', '-']) && split_base(value).1.trim_start().starts_with(['+', '-']);
+    match word.strip_prefix(['+', '-']) {
+        Some("") => true,
+        Some(duration) => Duration::parse(duration).is_some(),
+        None => ends_with_op,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::expr::{continues, eval, split_base, Duration};
+    use crate::unit::Unit;
+    use crate::value::{ValueError, ValueOptions};
+    use crate::zone::Zone;
+
+    use chrono::{DateTime, TimeZone, Utc};
+
+    fn at(s: &str, tz: &str) -> Result<DateTime<Utc>, ValueError> {
+        let opts = ValueOptions {
+            now: DateTime::from_timestamp(1758643530, 0).unwrap(),
+            ..Default::default()
+        };
+        let tz: Zone = tz.parse().unwrap();
+        eval(s, &opts, &tz).map(|parsed| parsed.dt)
+    }
+
+    #[test]
+    fn test_split_base() {
+        assert_eq!(split_base("1758643530+1h"), ("1758643530", "+1h"));
+        assert_eq!(split_base("m-1000 - 5ms"), ("m-1000", " - 5ms"));
+        assert_eq!(split_base("-m1000-5ms"), ("-m1000", "-5ms"));
+        assert_eq!(split_base("-1.5-1s"), ("-1.5", "-1s"));
+        assert_eq!(split_base("now-7d"), ("now", "-7d"));
+        assert_eq!(split_base("nope"), ("n", "ope"));
+    }
+
+    #[test]
+    fn test_duration_parse() {
+        assert_eq!(
+            Duration::parse("1y2mo3w4d"),
+            Some(Duration {
+                months: 14,
+                days: 25,
+                nanos: 0
+            })
+        );
+        assert_eq!(
+            Duration::parse("1h30m5s250ms3us7ns"),
+            Some(Duration {
+                months: 0,
+                days: 0,
+                nanos: 5_405_250_003_007
+            })
+        );
+        for s in ["", "1", "h", "1x", "1h30", "1.5h", "-1h"] {
+            assert_eq!(Duration::parse(s), None, "{s}");
+        }
+    }
+
+    #[test]
+    fn test_eval() {
+        let expected = |secs, nanos| Ok(DateTime::from_timestamp(secs, nanos).unwrap());
+        assert_eq!(at("1758643530+1h30m", "UTC"), expected(1758648930, 0));
+        assert_eq!(at("now-7d", "UTC"), expected(1758038730, 0));
+        assert_eq!(
+            at("m1758643530000 - 250ms", "UTC"),
+            expected(1758643529, 750_000_000)
+        );
+        assert_eq!(at("-86400 + 1d - 1s", "UTC"), expected(-1, 0));
+        assert_eq!(at("1758643530", "UTC"), expected(1758643530, 0));
+        assert_eq!(at("now", "UTC"), expected(1758643530, 0));
+    }
+
+    #[test]
+    fn test_eval_keeps_unit() {
+        let opts = ValueOptions::default();
+        let parsed = eval("u1758643530000000+1s", &opts, &"UTC".parse().unwrap());
+        assert_eq!(parsed.unwrap().unit, Unit::Micros);
+    }
+
+    #[test]
+    fn test_eval_calendar() {
+        let paris = chrono_tz::Europe::Paris;
+        let local = |y, mo, d, h| Ok(paris.with_ymd_and_hms(y, mo, d, h, 0, 0).unwrap().to_utc());
+        // 2025-03-29 12:00 in Paris, the day before the switch to summer time
+        let ts = paris
+            .with_ymd_and_hms(2025, 3, 29, 12, 0, 0)
+            .unwrap()
+            .timestamp();
+        assert_eq!(
+            at(&format!("{ts}+1d"), "Europe/Paris"),
+            local(2025, 3, 30, 12)
+        );
+        assert_eq!(
+            at(&format!("{ts}+24h"), "Europe/Paris"),
+            local(2025, 3, 30, 13)
+        );
+        // the last day of the month when the day doesn't exist
+        let ts = paris
+            .with_ymd_and_hms(2025, 1, 31, 12, 0, 0)
+            .unwrap()
+            .timestamp();
+        assert_eq!(
+            at(&format!("{ts}+1mo"), "Europe/Paris"),
+            local(2025, 2, 28, 12)
+        );
+        assert_eq!(
+            at(&format!("{ts}-1y"), "Europe/Paris"),
+            local(2024, 1, 31, 12)
+        );
+        // a skipped local time keeps the offset it had before
+        let ts = paris
+            .with_ymd_and_hms(2025, 3, 29, 2, 30, 0)
+            .unwrap()
+            .timestamp();
+        assert_eq!(
+            at(&format!("{ts}+1d"), "Europe/Paris"),
+            Ok(DateTime::from_timestamp(ts + 86400, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_eval_errors() {
+        assert_eq!(at("1758643530+1x", "UTC"), Err(ValueError::NotAnExpr));
+        assert_eq!(at("1758643530+", "UTC"), Err(ValueError::NotAnExpr));
+        assert_eq!(at("1758643530+1h 2h", "UTC"), Err(ValueError::NotAnExpr));
+        assert_eq!(at("nope+1h", "UTC"), Err(ValueError::NotAnInt));
+        assert_eq!(at("nowadays", "UTC"), Err(ValueError::NotAnInt));
+        assert_eq!(at("1758643530+300000y", "UTC"), Err(ValueError::NotATS));
+    }
+
+    #[test]
+    fn test_continues() {
+        assert!(continues("m1758643530", "-"));
+        assert!(continues("m1758643530", "-250ms"));
+        assert!(continues("m1758643530 -", "250ms"));
+        assert!(continues("now+", "1h"));
+        assert!(!continues("1758643530", "-86400"));
+        assert!(!continues("1758643530", "-m1000"));
+        assert!(!continues("1758643530", "250ms"));
+        assert!(!continues("-", "1758643530"));
+    }
+}
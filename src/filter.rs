@@ -0,0 +1,145 @@
+use std::io::{self, Write};
+
+use crate::process::FmtDate;
+use crate::value::Number;
+use crate::window::Window;
+use crate::zone::Zone;
+
+pub struct Filter {
+    fmt: String,
+    tz: Zone,
+    window: Window,
+    annotate: bool,
+}
+impl Filter {
+    pub fn new(fmt: String, tz: Zone, window: Window, annotate: bool) -> Filter {
+        Filter {
+            fmt,
+            tz,
+            window,
+            annotate,
+        }
+    }
+
+    /*
+     * Integer runs standing on their own (not glued to a word) are rewritten
+     * when they land in the window once read with one of the granularities,
+     * everything else in the line is left untouched.
+     */
+    pub fn line(&self, line: &str) -> String {
+        let mut out = String::with_capacity(line.len());
+        let mut rest = line;
+
+        while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+            let mut run_len = digits_len(&rest[start..]);
+            // a decimal fraction goes with its integer
+            if rest[start + run_len..].starts_with('.') {
+                let frac_len = digits_len(&rest[start + run_len + 1..]);
+                if frac_len > 0 {
+                    run_len += 1 + frac_len;
+                }
+            }
+            let run = &rest[start..start + run_len];
+            let before = rest[..start].chars().next_back();
+            let after = rest[start + run_len..].chars().next();
+
+            out.push_str(&rest[..start]);
+            match self.rewrite(run, before, after) {
+                Some(rendered) => out.push_str(&rendered),
+                None => out.push_str(run),
+            }
+            rest = &rest[start + run_len..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    fn rewrite(&self, run: &str, before: Option<char>, after: Option<char>) -> Option<String> {
+        if before.is_some_and(is_word_char) || after.is_some_and(is_word_char) {
+            return None;
+        }
+        let ts: Number = run.parse().ok()?;
+        let unit = self.window.guess_unit(ts.int)?;
+        let dt = ts.to_dt(unit)?;
+        let fmt_ts = FmtDate::new(dt, self.fmt.to_owned(), self.tz.clone());
+        if self.annotate {
+            Some(format!("{run} ({fmt_ts})"))
+        } else {
+            Some(format!("{fmt_ts}"))
+        }
+    }
+}
+
+fn digits_len(s: &str) -> usize {
+    s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len())
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+pub fn go(lines: impl Iterator<Item = String>, filter: Filter) -> io::Result<()> {
+    let mut out = io::stdout();
+    for line in lines {
+        writeln!(out, "{}", filter.line(&line))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::filter::Filter;
+    use crate::window::Window;
+
+    use chrono_tz::UTC;
+
+    fn filter(annotate: bool) -> Filter {
+        Filter::new(
+            "%Y-%m-%dT%H:%M:%S".to_string(),
+            UTC.into(),
+            Window::default(),
+            annotate,
+        )
+    }
+
+    #[test]
+    fn test_line_replace() {
+        let got = filter(false).line("level=info ts=1758643530123 msg=\"took 250ms\" pid=4242");
+        assert_eq!(
+            got,
+            "level=info ts=2025-09-23T16:05:30 msg=\"took 250ms\" pid=4242"
+        );
+    }
+
+    #[test]
+    fn test_line_annotate() {
+        let got = filter(true).line("1758643530 start, end 1758643590000000000");
+        assert_eq!(
+            got,
+            "1758643530 (2025-09-23T16:05:30) start, end 1758643590000000000 (2025-09-23T16:06:30)"
+        );
+    }
+
+    #[test]
+    fn test_line_fraction() {
+        let got = filter(false).line("t=1758643530.5 took 0.25s at 1758643590.");
+        assert_eq!(
+            got,
+            "t=2025-09-23T16:05:30 took 0.25s at 2025-09-23T16:06:30."
+        );
+    }
+
+    #[test]
+    fn test_line_untouched() {
+        let f = filter(false);
+        for line in [
+            "",
+            "no digits",
+            "id=a1758643530 req_1758643530 1758643530b",
+            "too big 17586435301234567890123",
+            "out of window 1337",
+        ] {
+            assert_eq!(f.line(line), line);
+        }
+    }
+}
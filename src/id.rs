@@ -0,0 +1,366 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+
+use crate::unit::Unit;
+use crate::value::{ts_from_str, Parsed, ValueError, ValueOptions};
+
+// Snowflake epochs known by name, in milliseconds since 1970.
+const EPOCHS: &[(&str, i64)] = &[
+    ("twitter", 1_288_834_974_657),
+    ("discord", 1_420_070_400_000),
+];
+pub const DEFAULT_EPOCH: &str = "twitter";
+
+const CROCKFORD: &str = "0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const BASE62: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+// 100 ns intervals from the Gregorian reform, 1582-10-15, to 1970.
+const GREGORIAN_OFFSET: i128 = 0x01B2_1DD2_1381_4000;
+const KSUID_EPOCH: i64 = 1_400_000_000;
+
+/*
+ * The kind of an ID and its parts other than the time, in layout order:
+ * numbers for counters, hexadecimal strings for random or node bytes.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdParts {
+    pub kind: &'static str,
+    pub parts: Vec<(&'static str, Value)>,
+}
+impl Serialize for IdParts {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut map = s.serialize_map(Some(self.parts.len() + 1))?;
+        map.serialize_entry("kind", self.kind)?;
+        for (name, value) in self.parts.iter() {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+impl std::fmt::Display for IdParts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "kind={}", self.kind)?;
+        for (name, value) in self.parts.iter() {
+            match value {
+                Value::String(value) => write!(f, "; {name}={value}")?,
+                value => write!(f, "; {name}={value}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// A known name, or a value in any unit (`m1420070400000`, `1420070400`).
+pub fn parse_epoch(epoch: &str) -> Result<DateTime<Utc>, ValueError> {
+    match EPOCHS.iter().find(|(name, _)| *name == epoch) {
+        Some((_, millis)) => DateTime::from_timestamp_millis(*millis).ok_or(ValueError::NotATS),
+        None => ts_from_str(epoch.to_string(), &ValueOptions::default()).map(|parsed| parsed.dt),
+    }
+}
+
+/*
+ * The instant embedded in an ID, its kind told by its shape:
+ * UUIDs (v1, v6 and v7, with or without hyphens), MongoDB ObjectIds (24 hex digits),
+ * ULIDs (26 Crockford base32 characters), KSUIDs (27 base62 characters),
+ * and snowflakes, integers counting milliseconds from `epoch` in their upper 42 bits.
+*/
+pub fn decode(id: &str, epoch: &DateTime<Utc>) -> Result<Parsed, ValueError> {
+    let is_hex = |x: &str| x.bytes().all(|b| b.is_ascii_hexdigit());
+    let hyphens = id.len() == 36 && [8, 13, 18, 23].iter().all(|&i| id.as_bytes()[i] == b'-');
+    let (dt, unit, parts) = if hyphens || (id.len() == 32 && is_hex(id)) {
+        uuid(&id.replacen('-', "", 4))?
+    } else if id.len() == 24 && is_hex(id) {
+        object_id(id)?
+    } else if id.len() == 26 {
+        ulid(id)?
+    } else if id.len() == 27 {
+        ksuid(id)?
+    } else if !id.is_empty() && id.len() <= 20 && id.bytes().all(|b| b.is_ascii_digit()) {
+        snowflake(id, epoch)?
+    } else {
+        return Err(ValueError::NotAnId);
+    };
+    Ok(Parsed {
+        dt,
+        unit,
+        guessed: false,
+        id: Some(parts),
+    })
+}
+
+type Decoded = (DateTime<Utc>, Unit, IdParts);
+
+fn parts(kind: &'static str, parts: Vec<(&'static str, Value)>) -> IdParts {
+    IdParts { kind, parts }
+}
+
+// Exactly `digits` hexadecimal digits: `from_str_radix` alone takes a leading `+`.
+fn hex_value(hex_str: &str, digits: usize) -> Result<u128, ValueError> {
+    if hex_str.len() != digits || !hex_str.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ValueError::NotAnId);
+    }
+    u128::from_str_radix(hex_str, 16).map_err(|_| ValueError::NotAnId)
+}
+
+fn hex(value: u128, digits: usize) -> Value {
+    Value::String(format!("{value:0digits$x}"))
+}
+
+fn from_nanos(nanos: i128) -> Result<DateTime<Utc>, ValueError> {
+    let secs = i64::try_from(nanos.div_euclid(1_000_000_000)).map_err(|_| ValueError::NotATS)?;
+    DateTime::from_timestamp(secs, nanos.rem_euclid(1_000_000_000) as u32).ok_or(ValueError::NotATS)
+}
+
+fn snowflake(id: &str, epoch: &DateTime<Utc>) -> Result<Decoded, ValueError> {
+    let id: u64 = id.parse().map_err(|_| ValueError::NotAnId)?;
+    let dt = epoch
+        .checked_add_signed(TimeDelta::milliseconds((id >> 22) as i64))
+        .ok_or(ValueError::NotATS)?;
+    let parts = parts(
+        "snowflake",
+        vec![
+            ("node", Value::from((id >> 12) & 0x3ff)),
+            ("sequence", Value::from(id & 0xfff)),
+        ],
+    );
+    Ok((dt, Unit::Millis, parts))
+}
+
+fn uuid(hex_str: &str) -> Result<Decoded, ValueError> {
+    let value = hex_value(hex_str, 32)?;
+    let version = (value >> 76) & 0xf;
+    let clock = |ticks: u128| -> Result<Decoded, ValueError> {
+        let dt = from_nanos((ticks as i128 - GREGORIAN_OFFSET) * 100)?;
+        let parts = parts(
+            "uuid",
+            vec![
+                ("version", Value::from(version as u64)),
+                ("clock_seq", Value::from(((value >> 48) & 0x3fff) as u64)),
+                ("node", hex(value & 0xffff_ffff_ffff, 12)),
+            ],
+        );
+        Ok((dt, Unit::Nanos, parts))
+    };
+    match version {
+        1 => clock((value >> 64 & 0xfff) << 48 | (value >> 80 & 0xffff) << 32 | value >> 96),
+        6 => clock((value >> 96) << 28 | (value >> 80 & 0xffff) << 12 | (value >> 64 & 0xfff)),
+        7 => {
+            let dt = from_nanos((value >> 80) as i128 * 1_000_000)?;
+            let parts = parts(
+                "uuid",
+                vec![
+                    ("version", Value::from(7)),
+                    ("rand_a", hex(value >> 64 & 0xfff, 3)),
+                    ("rand_b", hex(value & ((1 << 62) - 1), 16)),
+                ],
+            );
+            Ok((dt, Unit::Millis, parts))
+        }
+        _ => Err(ValueError::NotAnId),
+    }
+}
+
+fn object_id(id: &str) -> Result<Decoded, ValueError> {
+    let value = hex_value(id, 24)?;
+    let dt = DateTime::from_timestamp((value >> 64) as i64, 0).ok_or(ValueError::NotATS)?;
+    let parts = parts(
+        "objectid",
+        vec![
+            ("random", hex(value >> 24 & 0xff_ffff_ffff, 10)),
+            ("counter", Value::from((value & 0xff_ffff) as u64)),
+        ],
+    );
+    Ok((dt, Unit::Secs, parts))
+}
+
+/*
+ * Crockford base32 is case insensitive, and reads I and L as 1, O as 0;
+ * 26 characters hold 130 bits, the first one 3.
+*/
+fn ulid(id: &str) -> Result<Decoded, ValueError> {
+    let mut value: u128 = 0;
+    for (idx, c) in id.to_ascii_uppercase().chars().enumerate() {
+        let c = match c {
+            'I' | 'L' => '1',
+            'O' => '0',
+            c => c,
+        };
+        let digit = CROCKFORD.find(c).ok_or(ValueError::NotAnId)? as u128;
+        if idx == 0 && digit > 7 {
+            return Err(ValueError::NotAnId);
+        }
+        value = value << 5 | digit;
+    }
+    let dt = from_nanos((value >> 80) as i128 * 1_000_000)?;
+    let parts = parts(
+        "ulid",
+        vec![("randomness", hex(value & ((1 << 80) - 1), 20))],
+    );
+    Ok((dt, Unit::Millis, parts))
+}
+
+// 160 bits: the seconds since the KSUID epoch, then a 128 bits payload.
+fn ksuid(id: &str) -> Result<Decoded, ValueError> {
+    let mut bytes = [0u8; 20];
+    for c in id.chars() {
+        let mut carry = BASE62.find(c).ok_or(ValueError::NotAnId)? as u32;
+        for byte in bytes.iter_mut().rev() {
+            carry += *byte as u32 * 62;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        if carry != 0 {
+            return Err(ValueError::NotAnId);
+        }
+    }
+    let secs = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let payload = bytes[4..]
+        .iter()
+        .fold(0u128, |acc, b| acc << 8 | *b as u128);
+    let dt = DateTime::from_timestamp(KSUID_EPOCH + secs as i64, 0).ok_or(ValueError::NotATS)?;
+    let parts = parts("ksuid", vec![("payload", hex(payload, 32))]);
+    Ok((dt, Unit::Secs, parts))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::id::{decode, object_id, parse_epoch, IdParts};
+    use crate::unit::Unit;
+    use crate::value::ValueError;
+
+    use chrono::{DateTime, SecondsFormat, Utc};
+
+    fn twitter() -> DateTime<Utc> {
+        parse_epoch("twitter").unwrap()
+    }
+
+    fn instant(id: &str) -> String {
+        let parsed = decode(id, &twitter()).unwrap();
+        parsed.dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+    }
+
+    fn parts(id: &str) -> String {
+        decode(id, &twitter()).unwrap().id.unwrap().to_string()
+    }
+
+    #[test]
+    fn test_parse_epoch() {
+        assert_eq!(twitter().timestamp_millis(), 1288834974657);
+        let discord = parse_epoch("discord").unwrap();
+        assert_eq!(discord, parse_epoch("m1420070400000").unwrap());
+        assert_eq!(discord, parse_epoch("1420070400").unwrap());
+        assert_eq!(parse_epoch("nope"), Err(ValueError::NotAnInt));
+    }
+
+    #[test]
+    fn test_snowflake() {
+        assert_eq!(instant("1212161305326063616"), "2019-12-31T23:59:10.714Z");
+        assert_eq!(
+            parts("1212161305326063616"),
+            "kind=snowflake; node=753; sequence=0"
+        );
+        let discord = parse_epoch("discord").unwrap();
+        let parsed = decode("175928847299117063", &discord).unwrap();
+        assert_eq!(parsed.dt.timestamp_millis(), 1462015105796);
+        assert_eq!(parsed.unit, Unit::Millis);
+        assert_eq!(
+            parsed.id.unwrap().to_string(),
+            "kind=snowflake; node=32; sequence=7"
+        );
+    }
+
+    #[test]
+    fn test_uuid() {
+        let v1 = "c232ab00-9414-11ec-b3c8-9f6bdeced846";
+        assert_eq!(instant(v1), "2022-02-22T19:22:22Z");
+        assert_eq!(
+            parts(v1),
+            "kind=uuid; version=1; clock_seq=13256; node=9f6bdeced846"
+        );
+        let v6 = "1EC9414C-232A-6B00-B3C8-9F6BDECED846";
+        assert_eq!(instant(v6), "2022-02-22T19:22:22Z");
+        assert_eq!(
+            parts(v6),
+            "kind=uuid; version=6; clock_seq=13256; node=9f6bdeced846"
+        );
+        let v7 = "017f22e279b07cc398c4dc0c0c07398f";
+        assert_eq!(instant(v7), "2022-02-22T19:22:22Z");
+        assert_eq!(
+            parts(v7),
+            "kind=uuid; version=7; rand_a=cc3; rand_b=18c4dc0c0c07398f"
+        );
+        let v4 = "919108f7-52d1-4320-9bac-f847db4148a8";
+        assert_eq!(decode(v4, &twitter()), Err(ValueError::NotAnId));
+        for id in [
+            "+232ab00-9414-11ec-b3c8-9f6bdeced846",
+            "c232ab00-+414-11ec-b3c8-9f6bdeced846",
+            "c232ab0--9414-11ec-b3c8-9f6bdeced846",
+            "c232ab00-9414-11ec-b3c8-9f6bdeced84g",
+        ] {
+            assert_eq!(decode(id, &twitter()), Err(ValueError::NotAnId), "{id}");
+        }
+    }
+
+    #[test]
+    fn test_object_id() {
+        let id = "507f1f77bcf86cd799439011";
+        assert_eq!(instant(id), "2012-10-17T21:13:27Z");
+        assert_eq!(
+            parts(id),
+            "kind=objectid; random=bcf86cd799; counter=4427793"
+        );
+        let parsed = object_id("+07f1f77bcf86cd799439011");
+        assert_eq!(parsed, Err(ValueError::NotAnId));
+    }
+
+    #[test]
+    fn test_ulid() {
+        let id = "01ARZ3NDEKTSV4RRFFQ69G5FAV";
+        assert_eq!(instant(id), "2016-07-30T23:54:10.259Z");
+        assert_eq!(instant(&id.to_lowercase()), "2016-07-30T23:54:10.259Z");
+        assert_eq!(parts(id), "kind=ulid; randomness=d6764c61efb99302bd5b");
+        assert_eq!(
+            instant("OLARZ3NDEKTSV4RRFFQ69G5FAV"),
+            "2016-07-30T23:54:10.259Z"
+        );
+        assert_eq!(parts("0iarz3ndektsv4rrffq69g5fav"), parts(id));
+        assert_eq!(
+            decode("81ARZ3NDEKTSV4RRFFQ69G5FAV", &twitter()),
+            Err(ValueError::NotAnId)
+        );
+    }
+
+    #[test]
+    fn test_ksuid() {
+        let id = "0ujtsYcgvSTl8PAuAdqWYSMnLOv";
+        assert_eq!(instant(id), "2017-10-10T04:00:47Z");
+        assert_eq!(
+            parts(id),
+            "kind=ksuid; payload=b5a1cd34b5f99d1154fb6853345c9735"
+        );
+    }
+
+    #[test]
+    fn test_not_an_id() {
+        for id in [
+            "",
+            "nope",
+            "-1",
+            "123456789012345678901",
+            "0ujtsYcgvSTl8PAuAdqWYSMnL_v",
+        ] {
+            assert_eq!(decode(id, &twitter()), Err(ValueError::NotAnId), "{id}");
+        }
+    }
+
+    #[test]
+    fn test_id_parts_json() {
+        let parsed = decode("175928847299117063", &parse_epoch("discord").unwrap());
+        let id: IdParts = parsed.unwrap().id.unwrap();
+        assert_eq!(
+            serde_json::to_string(&id).unwrap(),
+            r#"{"kind":"snowflake","node":32,"sequence":7}"#
+        );
+    }
+}
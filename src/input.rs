@@ -0,0 +1,192 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal};
+
+use crate::args::{get_ts_strings, ArgsError};
+use crate::expr;
+
+const STDIN_FILE: &str = "-";
+
+pub type Lines = Box<dyn Iterator<Item = String>>;
+
+/*
+ * Positional values come first, then the content of each file (`-` being stdin).
+ * When there's neither, stdin is read, unless it's a terminal: nobody is
+ * going to type timestamps by hand, so the usual error is reported instead.
+*/
+pub fn get_lines(cli_args: Vec<String>, files: Vec<String>) -> Result<Lines, ArgsError> {
+    let (cli_args, files) = stdin_value(cli_args, files);
+    let values = match get_ts_strings(cli_args) {
+        Ok(values) => values,
+        Err(err) => {
+            if !files.is_empty() {
+                Vec::new()
+            } else if io::stdin().is_terminal() {
+                return Err(err);
+            } else {
+                return Ok(read_lines(Box::new(io::stdin().lock())));
+            }
+        }
+    };
+
+    let mut readers: Vec<Box<dyn BufRead>> = Vec::new();
+    for file in files.iter() {
+        readers.push(open(file)?);
+    }
+    Ok(Box::new(
+        values
+            .into_iter()
+            .chain(readers.into_iter().flat_map(read_lines)),
+    ))
+}
+
+pub fn get_values(cli_args: Vec<String>, files: Vec<String>) -> Result<Lines, ArgsError> {
+    Ok(split_words(get_lines(cli_args, files)?))
+}
+
+// A `-` value stands for stdin, read once with the files, as `-f -`.
+fn stdin_value(mut cli_args: Vec<String>, mut files: Vec<String>) -> (Vec<String>, Vec<String>) {
+    if cli_args.iter().skip(1).any(|x| x == STDIN_FILE) {
+        let program = cli_args.remove(0);
+        cli_args.retain(|x| x != STDIN_FILE);
+        cli_args.insert(0, program);
+        if !files.iter().any(|x| x == STDIN_FILE) {
+            files.push(STDIN_FILE.to_string());
+        }
+    }
+    (cli_args, files)
+}
+
+fn open(file: &str) -> Result<Box<dyn BufRead>, ArgsError> {
+    if file == STDIN_FILE {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    match File::open(file) {
+        Ok(f) => Ok(Box::new(BufReader::new(f))),
+        Err(err) => Err(ArgsError::File(format!("can't read '{file}': {err}"))),
+    }
+}
+
+// A read error (invalid UTF-8, I/O failure) ends the stream, with a warning.
+fn read_lines(reader: Box<dyn BufRead>) -> Lines {
+    Box::new(reader.lines().map_while(|line| match line {
+        Ok(line) => Some(line),
+        Err(err) => {
+            eprintln!("! input read error: {err}");
+            None
+        }
+    }))
+}
+
+// Words of an expression with spaces, like `m1758643530 - 250ms`, are kept together.
+fn split_words(lines: Lines) -> Lines {
+    Box::new(lines.flat_map(|line| {
+        let mut words: Vec<String> = Vec::new();
+        for word in line.split_whitespace() {
+            match words.last_mut() {
+                Some(last) if expr::continues(last, word) => {
+                    last.push(' ');
+                    last.push_str(word);
+                }
+                _ => words.push(word.to_string()),
+            }
+        }
+        words
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::args::ArgsError;
+    use crate::input::{get_lines, get_values, split_words, stdin_value};
+
+    use std::io::Write;
+
+    #[test]
+    fn test_get_values_args() {
+        let args: Vec<String> = vec!["tsp".to_string(), "argA".to_string(), "argB".to_string()];
+        let values: Vec<String> = get_values(args, Vec::new()).unwrap().collect();
+        assert_eq!(values, vec!["argA".to_string(), "argB".to_string()]);
+    }
+
+    #[test]
+    fn test_stdin_value() {
+        let strings = |x: &[&str]| x.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+        assert_eq!(
+            stdin_value(strings(&["tsp", "argA", "-"]), strings(&["f.txt"])),
+            (strings(&["tsp", "argA"]), strings(&["f.txt", "-"]))
+        );
+        assert_eq!(
+            stdin_value(strings(&["tsp", "-", "-"]), strings(&["-"])),
+            (strings(&["tsp"]), strings(&["-"]))
+        );
+        assert_eq!(
+            stdin_value(strings(&["tsp", "argA"]), Vec::new()),
+            (strings(&["tsp", "argA"]), Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_get_values_files() {
+        let path = std::env::temp_dir().join("tsp_test_get_values_files.txt");
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(
+            file,
+            "1758643530 m1758643530000\n\n  n1758643530000000000  "
+        )
+        .unwrap();
+
+        let args: Vec<String> = vec!["tsp".to_string(), "argA".to_string()];
+        let files = vec![path.to_string_lossy().to_string()];
+        let values: Vec<String> = get_values(args, files).unwrap().collect();
+        assert_eq!(
+            values,
+            vec![
+                "argA".to_string(),
+                "1758643530".to_string(),
+                "m1758643530000".to_string(),
+                "n1758643530000000000".to_string(),
+            ]
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_get_lines_missing_file() {
+        let args: Vec<String> = vec!["tsp".to_string()];
+        let files = vec!["/nonexistent/tsp/values.txt".to_string()];
+        let error = get_lines(args, files).err().unwrap();
+        assert!(matches!(error, ArgsError::File(_)));
+    }
+
+    #[test]
+    fn test_split_words() {
+        let lines =
+            Box::new(vec!["a b".to_string(), "".to_string(), "\tc ".to_string()].into_iter());
+        let words: Vec<String> = split_words(lines).collect();
+        assert_eq!(
+            words,
+            vec!["a".to_string(), "b".to_string(), "c".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_split_words_expressions() {
+        let lines = Box::new(
+            vec![
+                "m1758643530 - 250ms now+1d -86400".to_string(),
+                "1758643530 -1h +30m".to_string(),
+            ]
+            .into_iter(),
+        );
+        let words: Vec<String> = split_words(lines).collect();
+        assert_eq!(
+            words,
+            vec![
+                "m1758643530 - 250ms".to_string(),
+                "now+1d".to_string(),
+                "-86400".to_string(),
+                "1758643530 -1h +30m".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use chrono::{DateTime, Utc};
+use std::io;
+
+mod args;
+mod config;
+mod delta;
+mod diff;
+mod dump;
+mod expr;
+mod filter;
+mod id;
+mod input;
+mod outcome;
+mod parse;
+mod posix;
+mod preset;
+mod process;
+mod relative;
+mod reverse;
+mod select;
+mod stats;
+mod status;
+mod unit;
+mod usage;
+mod value;
+mod window;
+mod zone;
+use args::{get_annotate, get_auto, get_delta, get_diff, get_filter, get_pairs, get_window};
+use args::{get_files, get_fmt_str, get_help, get_ids, get_list_formats, get_sink, get_tzs};
+use args::{
+    get_relative, get_reverse, get_select, get_show_config, get_stats, get_strict, get_unit,
+};
+use diff::Span;
+use filter::Filter;
+
+pub use args::ArgsError;
+pub use config::{Config, ConfigError};
+pub use id::IdParts;
+pub use outcome::{Outcome, OutcomeStatus};
+pub use parse::InputParseError;
+pub use process::{FmtDate, FmtError};
+pub use status::Status;
+pub use unit::Unit;
+pub use value::{Parsed, ValueError, ValueOptions};
+pub use window::{Window, WindowParseError};
+pub use zone::{Zone, ZoneParseError};
+
+/*
+ * A value as given on the command line: an optional unit prefix and sign,
+ * then an integer with an optional decimal fraction (`m-1758643530123.5`).
+*/
+pub fn parse_value(value: &str, opts: &ValueOptions) -> Result<Parsed, ValueError> {
+    value::ts_from_str(value.to_string(), opts)
+}
+
+/*
+ * `fmt` is a preset name (`rfc3339`, `sql`...) or a strftime string.
+ * The `http` preset is always rendered in UTC, whatever `tz`.
+*/
+pub fn format_instant(dt: DateTime<Utc>, fmt: &str, tz: &Zone) -> Result<String, FmtError> {
+    let fmt = resolve_fmt(fmt)?;
+    let tz = fmt_tzs(&fmt, std::slice::from_ref(tz)).remove(0);
+    Ok(FmtDate::new(dt, fmt, tz).to_string())
+}
+
+/*
+ * Values converted as tsp does by default, each one giving an `Outcome`,
+ * rendered in `tzs`: invalid values give failed outcomes, not an error.
+*/
+pub fn convert<I, S>(
+    values: I,
+    fmt: &str,
+    tzs: &[Zone],
+    opts: ValueOptions,
+) -> Result<Vec<Outcome>, FmtError>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let fmt = resolve_fmt(fmt)?;
+    let tzs = fmt_tzs(&fmt, tzs);
+    let values = values.into_iter().map(Into::into);
+    let mut outcomes: Vec<Outcome> = Vec::new();
+    // collecting in a vector can't fail
+    let extras = process::Extras::default();
+    process::go(values, fmt, tzs, opts, extras, false, &mut outcomes).expect("collecting outcomes");
+    Ok(outcomes)
+}
+
+fn resolve_fmt(fmt: &str) -> Result<String, FmtError> {
+    let fmt = preset::get_preset(fmt).map_or(fmt, |preset| preset.fmt);
+    process::check_fmt(fmt)?;
+    Ok(fmt.to_string())
+}
+
+fn fmt_tzs(fmt: &str, tzs: &[Zone]) -> Vec<Zone> {
+    if preset::in_utc(fmt) {
+        vec![Zone::from(chrono_tz::UTC)]
+    } else {
+        tzs.to_vec()
+    }
+}
+
+// The whole command, from its arguments to the status it exits with.
+pub fn run(cli_args: Vec<String>) -> Status {
+    run_with(cli_args, config::load)
+}
+
+/*
+ * The command with the configuration given by `load`, rather than the one of
+ * the user's files and environment; it's called once the arguments are parsed.
+*/
+pub fn run_with<F>(cli_args: Vec<String>, load: F) -> Status
+where
+    F: FnOnce() -> Result<Config, ConfigError>,
+{
+    let cli = match args::parse(cli_args) {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("{err}");
+            return Status::Usage;
+        }
+    };
+    if get_help(&cli) {
+        usage::print_usage();
+        return Status::Converted;
+    }
+    let cli = match load() {
+        Ok(config) => cli.with_config(config),
+        Err(err) => {
+            eprintln!("{err}");
+            return Status::Usage;
+        }
+    };
+    if get_show_config(&cli) {
+        println!("{}", args::show_config(&cli));
+        return Status::Converted;
+    }
+    if let Err(err) = args::check_mode(&cli) {
+        eprintln!("{err}");
+        return Status::Usage;
+    }
+    let fmt_str = match get_fmt_str(&cli) {
+        Ok(fmt_str) => fmt_str,
+        Err(err) => {
+            eprintln!("{err}");
+            return Status::Usage;
+        }
+    };
+    let tzs = match get_tzs(&cli) {
+        Ok(tzs) => tzs,
+        Err(err) => {
+            eprintln!("{err}");
+            return Status::Usage;
+        }
+    };
+    if get_list_formats(&cli) {
+        println!("{}", args::list_formats(&cli, &tzs[0], chrono::Utc::now()));
+        return Status::Converted;
+    }
+    let files = get_files(&cli);
+    let opts = ValueOptions {
+        auto: get_auto(&cli),
+        window: get_window(&cli),
+        ..Default::default()
+    };
+    let strict = get_strict(&cli);
+    if get_diff(&cli) {
+        let mut sink = match get_sink::<Span>(&cli) {
+            Ok(sink) => sink,
+            Err(err) => {
+                eprintln!("{err}");
+                return Status::Usage;
+            }
+        };
+        let pairs = get_pairs(&cli);
+        let tz = tzs[0].clone();
+        return exit_status(
+            input::get_values(cli.args, files)
+                .map(|values| diff::go(values, tz, opts, pairs, strict, sink.as_mut())),
+        );
+    }
+    let mut sink = match get_sink(&cli) {
+        Ok(sink) => sink,
+        Err(err) => {
+            eprintln!("{err}");
+            return Status::Usage;
+        }
+    };
+    let filter = get_filter(&cli);
+    let annotate = get_annotate(&cli);
+    let reverse = get_reverse(&cli);
+    let unit = get_unit(&cli);
+    let select = match get_select(&cli, &opts, &tzs[0]) {
+        Ok(select) => select,
+        Err(err) => {
+            eprintln!("{err}");
+            return Status::Usage;
+        }
+    };
+    let ids = match get_ids(&cli) {
+        Ok(ids) => ids,
+        Err(err) => {
+            eprintln!("{err}");
+            return Status::Usage;
+        }
+    };
+    let extras = process::Extras {
+        ids,
+        select,
+        relative: get_relative(&cli, &opts, &tzs[0]),
+        delta: get_delta(&cli),
+        stats: get_stats(&cli),
+    };
+    exit_status(if filter || annotate {
+        input::get_lines(cli.args, files).map(|lines| {
+            let tz = tzs[0].clone();
+            filter::go(lines, Filter::new(fmt_str, tz, opts.window, annotate))
+                .map(|_| Status::Converted)
+        })
+    } else if reverse {
+        input::get_lines(cli.args, files).map(|date_strs| {
+            let tz = tzs[0].clone();
+            reverse::go(date_strs, fmt_str, tz, unit, strict, sink.as_mut())
+        })
+    } else {
+        input::get_values(cli.args, files)
+            .map(|ts_strs| process::go(ts_strs, fmt_str, tzs, opts, extras, strict, sink.as_mut()))
+    })
+}
+
+fn exit_status(outcome: Result<io::Result<Status>, ArgsError>) -> Status {
+    match outcome {
+        Ok(Ok(status)) => status,
+        // the reader went away, as with `| head`
+        Ok(Err(err)) if err.kind() == io::ErrorKind::BrokenPipe => Status::Converted,
+        Ok(Err(err)) => {
+            eprintln!("! output error: {err}");
+            Status::Failed
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            Status::Usage
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{convert, format_instant, parse_value};
+    use crate::{FmtError, Outcome, OutcomeStatus, Unit, ValueError, ValueOptions, Zone};
+
+    use chrono::DateTime;
+
+    #[test]
+    fn test_parse_value() {
+        let parsed = parse_value("m1758643530123", &ValueOptions::default()).unwrap();
+        assert_eq!(
+            parsed.dt,
+            DateTime::from_timestamp_millis(1758643530123).unwrap()
+        );
+        assert_eq!(parsed.unit, Unit::Millis);
+        assert!(!parsed.guessed);
+
+        let error = parse_value("nope", &ValueOptions::default()).unwrap_err();
+        assert_eq!(error, ValueError::NotAnInt);
+    }
+
+    #[test]
+    fn test_format_instant() {
+        let dt = DateTime::from_timestamp(1758643530, 0).unwrap();
+        let paris: Zone = "Europe/Paris".parse().unwrap();
+        assert_eq!(
+            format_instant(dt, "rfc3339", &paris).unwrap(),
+            "2025-09-23T18:05:30+02:00"
+        );
+        assert_eq!(format_instant(dt, "%H:%M", &paris).unwrap(), "18:05");
+        assert_eq!(
+            format_instant(dt, "%H:%Q", &paris).unwrap_err(),
+            FmtError {
+                spec: "%Q".to_string(),
+                column: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_format_instant_http() {
+        let dt = DateTime::from_timestamp(1758643530, 0).unwrap();
+        let paris: Zone = "Europe/Paris".parse().unwrap();
+        assert_eq!(
+            format_instant(dt, "http", &paris).unwrap(),
+            "Tue, 23 Sep 2025 16:05:30 GMT"
+        );
+        let outcomes = convert(["1758643530"], "http", &[paris], ValueOptions::default());
+        assert_eq!(
+            outcomes.unwrap()[0].value_out(),
+            "Tue, 23 Sep 2025 16:05:30 GMT"
+        );
+    }
+
+    #[test]
+    fn test_convert() {
+        let tzs: Vec<Zone> = vec!["UTC".parse().unwrap()];
+        let outcomes = convert(
+            ["1758643530", "nope"],
+            "date",
+            &tzs,
+            ValueOptions::default(),
+        );
+        let outcomes: Vec<Outcome> = outcomes.unwrap();
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(outcomes[0].value_out(), "2025-09-23");
+        assert_eq!(outcomes[0].status(), OutcomeStatus::Ok);
+        assert_eq!(outcomes[0].unit(), Some(Unit::Secs));
+        assert_eq!(outcomes[0].epoch_ns(), Some(1_758_643_530_000_000_000));
+        assert_eq!(outcomes[1].value_in(), "nope");
+        assert_eq!(outcomes[1].status(), OutcomeStatus::Error);
+        assert_eq!(outcomes[1].error(), Some("not_an_integer"));
+
+        assert!(convert(["1"], "%Q", &tzs, ValueOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        fn is_error(_: &dyn std::error::Error) {}
+        is_error(&ValueError::NotAnInt);
+        is_error(&crate::InputParseError);
+        is_error(&crate::ArgsError::File("x".to_string()));
+        is_error(&FmtError {
+            spec: "%Q".to_string(),
+            column: 1,
+        });
+        is_error(&crate::WindowParseError);
+        is_error(&crate::ZoneParseError);
+    }
+
+    #[test]
+    fn test_unrenderable_format() {
+        let dt = DateTime::from_timestamp(1758643530, 0).unwrap();
+        let utc: Zone = "UTC".parse().unwrap();
+        let err = FmtError {
+            spec: "%#z".to_string(),
+            column: 7,
+        };
+        assert_eq!(format_instant(dt, "%H:%M %#z", &utc).unwrap_err(), err);
+        let outcomes = convert(["1"], "%H:%M %#z", &[utc], ValueOptions::default());
+        assert_eq!(outcomes.unwrap_err(), err);
+    }
+}
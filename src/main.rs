@@ -1,39 +1,86 @@
 use std::env;
+use std::process::ExitCode;
 
-mod args;
-mod dump;
-mod outcome;
-mod parse;
-mod process;
-mod unit;
-mod usage;
-mod value;
-use args::{cleanup, get_dump_fn, get_fmt_str, get_tz};
-
-fn main() {
+fn main() -> ExitCode {
     let cli_args: Vec<String> = env::args().collect();
-    do_it(cli_args.to_owned());
+    do_it(cli_args.to_owned())
 }
 
-fn do_it(mut cli_args: Vec<String>) {
-    if usage::is_help(cli_args.to_owned()) {
-        usage::print_usage();
-    } else {
-        let dump_fn = get_dump_fn(&mut cli_args);
-        let fmt_str = get_fmt_str(&mut cli_args);
-        let tz = get_tz(&mut cli_args);
-        if cleanup(&mut cli_args) {
-            eprintln!("! cleaned rubbish parameters");
-        }
-        process::go(cli_args.to_owned(), fmt_str, tz, dump_fn);
-    }
+fn do_it(cli_args: Vec<String>) -> ExitCode {
+    tsp::run(cli_args).into()
 }
 
 #[cfg(test)]
 mod test_dummy {
     #[test]
-    fn test_default() {
-        crate::main()
+    fn test_do_it_file() {
+        let path = std::env::temp_dir().join("tsp_test_do_it_file.txt");
+        std::fs::write(&path, "1758643530\nm1758643530000\n").unwrap();
+        crate::do_it(vec![
+            "tsp".to_string(),
+            "-f".to_string(),
+            path.to_string_lossy().to_string(),
+        ]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_do_it_filter() {
+        crate::do_it(vec![
+            "tsp".to_string(),
+            "--filter".to_string(),
+            "ts=1758643530123 msg=ok".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_do_it_reverse() {
+        crate::do_it(vec![
+            "tsp".to_string(),
+            "-r".to_string(),
+            "-U".to_string(),
+            "n".to_string(),
+            "2025-09-23T16:05:30Z".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_do_it_unknown_tz() {
+        let code = crate::do_it(vec![
+            "tsp".to_string(),
+            "-T".to_string(),
+            "Europe/Pari".to_string(),
+            "1758643530".to_string(),
+        ]);
+        assert_eq!(code, std::process::ExitCode::from(2));
+    }
+
+    #[test]
+    fn test_run_status() {
+        let args = |values: &[&str]| {
+            let mut args = vec!["tsp".to_string()];
+            args.extend(values.iter().map(|x| x.to_string()));
+            args
+        };
+        // the user's configuration and environment are left out
+        let run = |args: Vec<String>| tsp::run_with(args, || Ok(tsp::Config::default()));
+        assert_eq!(run(args(&["1758643530"])), tsp::Status::Converted);
+        assert_eq!(run(args(&["1758643530", "nope"])), tsp::Status::Failed);
+        assert_eq!(
+            run(args(&["--strict", "nope", "1758643530"])),
+            tsp::Status::Failed
+        );
+        assert_eq!(
+            run(args(&["-r", "2025-09-23T16:05:30Z", "nope"])),
+            tsp::Status::Failed
+        );
+        assert_eq!(run(args(&["-f", "/nonexistent/tsp"])), tsp::Status::Usage);
+        assert_eq!(
+            run(args(&["--output", "xml", "1758643530"])),
+            tsp::Status::Usage
+        );
+        assert_eq!(run(args(&["diff", "1", "2"])), tsp::Status::Converted);
+        assert_eq!(run(args(&["diff", "1"])), tsp::Status::Usage);
     }
 
     #[test]
@@ -42,12 +89,12 @@ mod test_dummy {
     }
 
     #[test]
-    fn test_warning() {
+    fn test_do_it_unknown_option() {
         let args: Vec<String> = vec![
             "tsp".to_string(),
             "-rubbish".to_string(),
             "argA".to_string(),
         ];
-        crate::do_it(args);
+        assert_eq!(crate::do_it(args), std::process::ExitCode::from(2));
     }
 }
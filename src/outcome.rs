@@ -1,13 +1,111 @@
-use serde::Serialize;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Serialize, Serializer};
 
+use crate::delta::Step;
+use crate::diff::human;
+use crate::dump::Record;
+use crate::id::IdParts;
+use crate::unit::Unit;
+use crate::value::ValueError;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutcomeStatus {
+    Ok,
+    Error,
+}
+impl std::fmt::Display for OutcomeStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutcomeStatus::Ok => write!(f, "ok"),
+            OutcomeStatus::Error => write!(f, "error"),
+        }
+    }
+}
+
+// Columns of tabular outputs, in the order of `Outcome::row`.
+pub const COLUMNS: &[&str] = &[
+    "value_in",
+    "value_out",
+    "status",
+    "error",
+    "unit",
+    "guessed",
+    "epoch_ns",
+    "instant",
+    "zones",
+    "relative",
+    "since_previous_ns",
+    "since_first_ns",
+    "out_of_order",
+    "id",
+];
+
+/*
+ * `value_out` is what text output shows, a rendering or an error message.
+ * The other fields are for machine readable outputs: `error` holds the code
+ * of the failure, `epoch_ns` and `instant` the converted instant.
+ * Along a sequence, `since_previous_ns` and `since_first_ns` are the time elapsed
+ * from the previous and the first valid values.
+ * `id` is the kind and the other parts of an ID the instant comes from.
+*/
 #[derive(Serialize, Clone, Debug, PartialEq, Eq)]
 pub struct Outcome {
     value_in: String,
     value_out: String,
+    status: OutcomeStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unit: Option<Unit>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    guessed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    epoch_ns: Option<i128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instant: Option<String>,
+    // renderings per timezone, only set when there are several of them
+    #[serde(
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_zones"
+    )]
+    zones: Vec<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    relative: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    since_previous_ns: Option<i128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    since_first_ns: Option<i128>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    out_of_order: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<IdParts>,
 }
 impl std::fmt::Display for Outcome {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{0:20} :: {1}", self.value_in, self.value_out)
+        write!(f, "{0:20} :: {1}", self.value_in, self.value_out)?;
+        // the first zone is already in `value_out`
+        for (_, rendering) in self.zones.iter().skip(1) {
+            write!(f, " :: {rendering}")?;
+        }
+        if let Some(unit) = self.unit.filter(|_| self.guessed) {
+            write!(f, " ({unit})")?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, " ({})", id.kind)?;
+        }
+        if let Some(relative) = &self.relative {
+            write!(f, " :: {relative}")?;
+        }
+        // nothing to show for the first value
+        if let (Some(previous), Some(first)) = (self.since_previous_ns, self.since_first_ns) {
+            let (previous, first) = (signed(previous), signed(first));
+            write!(f, " :: {previous} since previous, {first} since first")?;
+            if self.out_of_order {
+                write!(f, " (out of order)")?;
+            }
+        }
+        Ok(())
     }
 }
 impl Outcome {
@@ -15,6 +113,18 @@ impl Outcome {
         Outcome {
             value_in: ts_str.to_string(),
             value_out: "".into(),
+            status: OutcomeStatus::Ok,
+            error: None,
+            unit: None,
+            guessed: false,
+            epoch_ns: None,
+            instant: None,
+            zones: Vec::new(),
+            relative: None,
+            since_previous_ns: None,
+            since_first_ns: None,
+            out_of_order: false,
+            id: None,
         }
     }
 
@@ -22,15 +132,317 @@ impl Outcome {
         self.value_out = out;
         self.to_owned()
     }
+
+    pub fn set_error(&mut self, err: &ValueError) -> Outcome {
+        self.value_out = format!("{err}");
+        self.status = OutcomeStatus::Error;
+        self.error = Some(err.code());
+        self.to_owned()
+    }
+
+    // Text output only shows guessed units.
+    pub fn set_unit(&mut self, unit: Unit, guessed: bool) -> Outcome {
+        self.unit = Some(unit);
+        self.guessed = guessed;
+        self.to_owned()
+    }
+
+    pub fn set_instant(&mut self, dt: &DateTime<Utc>) -> Outcome {
+        let nanos = dt.timestamp() as i128 * 1_000_000_000 + dt.timestamp_subsec_nanos() as i128;
+        self.epoch_ns = Some(nanos);
+        self.instant = Some(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true));
+        self.to_owned()
+    }
+
+    // `value_out` gets the rendering of the first zone.
+    pub fn set_zones(&mut self, zones: Vec<(String, String)>) -> Outcome {
+        if let Some((_, rendering)) = zones.first() {
+            self.value_out = rendering.to_owned();
+        }
+        if zones.len() > 1 {
+            self.zones = zones;
+        }
+        self.to_owned()
+    }
+
+    pub fn set_relative(&mut self, relative: String) -> Outcome {
+        self.relative = Some(relative);
+        self.to_owned()
+    }
+
+    pub fn set_step(&mut self, step: &Step) -> Outcome {
+        self.since_previous_ns = step.since_previous;
+        self.since_first_ns = Some(step.since_first);
+        self.out_of_order = step.out_of_order;
+        self.to_owned()
+    }
+
+    pub fn set_id(&mut self, id: IdParts) -> Outcome {
+        self.id = Some(id);
+        self.to_owned()
+    }
+
+    pub fn value_in(&self) -> &str {
+        &self.value_in
+    }
+
+    pub fn value_out(&self) -> &str {
+        &self.value_out
+    }
+
+    pub fn status(&self) -> OutcomeStatus {
+        self.status
+    }
+
+    pub fn error(&self) -> Option<&'static str> {
+        self.error
+    }
+
+    pub fn unit(&self) -> Option<Unit> {
+        self.unit
+    }
+
+    pub fn guessed(&self) -> bool {
+        self.guessed
+    }
+
+    pub fn epoch_ns(&self) -> Option<i128> {
+        self.epoch_ns
+    }
+
+    pub fn instant(&self) -> Option<&str> {
+        self.instant.as_deref()
+    }
+
+    pub fn zones(&self) -> &[(String, String)] {
+        &self.zones
+    }
+
+    pub fn relative(&self) -> Option<&str> {
+        self.relative.as_deref()
+    }
+
+    pub fn since_previous_ns(&self) -> Option<i128> {
+        self.since_previous_ns
+    }
+
+    pub fn since_first_ns(&self) -> Option<i128> {
+        self.since_first_ns
+    }
+
+    pub fn out_of_order(&self) -> bool {
+        self.out_of_order
+    }
+
+    pub fn id(&self) -> Option<&IdParts> {
+        self.id.as_ref()
+    }
+}
+impl Record for Outcome {
+    const COLUMNS: &'static [&'static str] = COLUMNS;
+
+    // Fields as strings, empty when unset; zones are `name=rendering`, `; ` separated.
+    fn row(&self) -> Vec<String> {
+        let zones: Vec<String> = self
+            .zones
+            .iter()
+            .map(|(name, rendering)| format!("{name}={rendering}"))
+            .collect();
+        vec![
+            self.value_in.to_owned(),
+            self.value_out.to_owned(),
+            self.status.to_string(),
+            self.error.unwrap_or_default().to_string(),
+            self.unit.map(|x| x.to_string()).unwrap_or_default(),
+            if self.guessed { "true" } else { "" }.to_string(),
+            self.epoch_ns.map(|x| x.to_string()).unwrap_or_default(),
+            self.instant.to_owned().unwrap_or_default(),
+            zones.join("; "),
+            self.relative.to_owned().unwrap_or_default(),
+            self.since_previous_ns
+                .map(|x| x.to_string())
+                .unwrap_or_default(),
+            self.since_first_ns
+                .map(|x| x.to_string())
+                .unwrap_or_default(),
+            if self.out_of_order { "true" } else { "" }.to_string(),
+            self.id.as_ref().map(|x| x.to_string()).unwrap_or_default(),
+        ]
+    }
+}
+
+// `+1m 5s`, the sign being explicit.
+fn signed(nanos: i128) -> String {
+    match nanos {
+        1.. => format!("+{}", human(nanos)),
+        _ => human(nanos),
+    }
+}
+
+// Keeps the order of the zones, as given on the command line.
+fn serialize_zones<S: Serializer>(zones: &[(String, String)], s: S) -> Result<S::Ok, S::Error> {
+    s.collect_map(zones.iter().map(|(k, v)| (k, v)))
 }
 
 #[cfg(test)]
 mod test {
-    use crate::outcome::Outcome;
+    use crate::delta::Step;
+    use crate::dump::Record;
+    use crate::outcome::{Outcome, COLUMNS};
+    use crate::unit::Unit;
+    use crate::value::ValueError;
+
+    use chrono::DateTime;
 
     #[test]
     fn test_outcome_implementation() {
         let r = Outcome::new("foo".to_string()).set("bar".to_string());
         assert_eq!(format!("{r}"), "foo                  :: bar");
     }
+
+    #[test]
+    fn test_outcome_guessed_unit() {
+        let r = Outcome::new("foo".to_string())
+            .set("bar".to_string())
+            .set_unit(Unit::Millis, true);
+        assert_eq!(format!("{r}"), "foo                  :: bar (millis)");
+        assert_eq!(
+            serde_json::to_string(&r).unwrap(),
+            r#"{"value_in":"foo","value_out":"bar","status":"ok","unit":"millis","guessed":true}"#
+        );
+    }
+
+    #[test]
+    fn test_outcome_unit() {
+        let r = Outcome::new("foo".to_string())
+            .set("bar".to_string())
+            .set_unit(Unit::Millis, false);
+        assert_eq!(format!("{r}"), "foo                  :: bar");
+        assert_eq!(
+            serde_json::to_string(&r).unwrap(),
+            r#"{"value_in":"foo","value_out":"bar","status":"ok","unit":"millis"}"#
+        );
+    }
+
+    #[test]
+    fn test_outcome_error() {
+        let r = Outcome::new("foo".to_string()).set_error(&ValueError::NotAnInt);
+        assert_eq!(
+            format!("{r}"),
+            "foo                  :: the value is not an integer"
+        );
+        assert_eq!(
+            serde_json::to_string(&r).unwrap(),
+            r#"{"value_in":"foo","value_out":"the value is not an integer","status":"error","error":"not_an_integer"}"#
+        );
+    }
+
+    #[test]
+    fn test_outcome_instant() {
+        let dt = DateTime::from_timestamp(1758643530, 123_000_000).unwrap();
+        let r = Outcome::new("foo".to_string())
+            .set("bar".to_string())
+            .set_instant(&dt);
+        assert_eq!(format!("{r}"), "foo                  :: bar");
+        assert_eq!(
+            serde_json::to_string(&r).unwrap(),
+            r#"{"value_in":"foo","value_out":"bar","status":"ok","epoch_ns":1758643530123000000,"instant":"2025-09-23T16:05:30.123Z"}"#
+        );
+
+        let dt = DateTime::from_timestamp(-1, 0).unwrap();
+        let r = Outcome::new("foo".to_string()).set_instant(&dt);
+        assert!(serde_json::to_string(&r)
+            .unwrap()
+            .contains(r#""epoch_ns":-1000000000,"instant":"1969-12-31T23:59:59Z""#));
+    }
+
+    #[test]
+    fn test_outcome_relative() {
+        let r = Outcome::new("foo".to_string())
+            .set("bar".to_string())
+            .set_relative("3 hours ago".to_string());
+        assert_eq!(format!("{r}"), "foo                  :: bar :: 3 hours ago");
+        assert_eq!(
+            serde_json::to_string(&r).unwrap(),
+            r#"{"value_in":"foo","value_out":"bar","status":"ok","relative":"3 hours ago"}"#
+        );
+    }
+
+    #[test]
+    fn test_outcome_zones() {
+        let r = Outcome::new("foo".to_string()).set_zones(vec![
+            ("Europe/Paris".to_string(), "bar".to_string()),
+            ("Asia/Tokyo".to_string(), "baz".to_string()),
+        ]);
+        assert_eq!(format!("{r}"), "foo                  :: bar :: baz");
+        assert_eq!(
+            serde_json::to_string(&r).unwrap(),
+            r#"{"value_in":"foo","value_out":"bar","status":"ok","zones":{"Europe/Paris":"bar","Asia/Tokyo":"baz"}}"#
+        );
+    }
+
+    #[test]
+    fn test_outcome_row() {
+        let r = Outcome::new("foo".to_string())
+            .set_zones(vec![
+                ("Europe/Paris".to_string(), "bar".to_string()),
+                ("Asia/Tokyo".to_string(), "baz".to_string()),
+            ])
+            .set_unit(Unit::Secs, true)
+            .set_instant(&DateTime::from_timestamp(1, 0).unwrap());
+        assert_eq!(r.row().len(), COLUMNS.len());
+        assert_eq!(
+            r.row(),
+            vec![
+                "foo",
+                "bar",
+                "ok",
+                "",
+                "secs",
+                "true",
+                "1000000000",
+                "1970-01-01T00:00:01Z",
+                "Europe/Paris=bar; Asia/Tokyo=baz",
+                "",
+                "",
+                "",
+                "",
+                ""
+            ]
+        );
+        let r = Outcome::new("foo".to_string()).set_error(&ValueError::NotATS);
+        assert_eq!(r.row()[2..4], ["error", "not_a_timestamp"]);
+    }
+
+    #[test]
+    fn test_outcome_step() {
+        let step = |since_previous, since_first, out_of_order| Step {
+            since_previous,
+            since_first,
+            out_of_order,
+        };
+        let r = Outcome::new("foo".to_string())
+            .set("bar".to_string())
+            .set_step(&step(None, 0, false));
+        assert_eq!(format!("{r}"), "foo                  :: bar");
+        let r = Outcome::new("foo".to_string())
+            .set("bar".to_string())
+            .set_step(&step(Some(-30_000_000_000), 90_000_000_000, true));
+        assert_eq!(
+            format!("{r}"),
+            "foo                  :: bar :: -30s since previous, +1m 30s since first (out of order)"
+        );
+        assert_eq!(
+            serde_json::to_string(&r).unwrap(),
+            r#"{"value_in":"foo","value_out":"bar","status":"ok","since_previous_ns":-30000000000,"since_first_ns":90000000000,"out_of_order":true}"#
+        );
+        assert_eq!(r.row()[10..13], ["-30000000000", "90000000000", "true"]);
+    }
+
+    #[test]
+    fn test_outcome_single_zone() {
+        let r = Outcome::new("foo".to_string())
+            .set_zones(vec![("Europe/Paris".to_string(), "bar".to_string())]);
+        assert_eq!(r, Outcome::new("foo".to_string()).set("bar".to_string()));
+    }
 }
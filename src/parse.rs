@@ -4,6 +4,12 @@ use std::str::FromStr;
 
 #[derive(Debug)]
 pub struct InputParseError;
+impl std::fmt::Display for InputParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "expected a unit prefix or a digit")
+    }
+}
+impl std::error::Error for InputParseError {}
 
 #[derive(Debug, PartialEq)]
 pub struct ParseResult {
@@ -14,22 +20,40 @@ impl FromStr for ParseResult {
     type Err = InputParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let prefix = &s.to_string()[0..1];
-        match prefix {
-            "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" => Ok(ParseResult {
-                unit: Unit::Secs,
-                ts: s.into(),
+        ParseResult::with_default(s, Unit::Secs)
+    }
+}
+impl ParseResult {
+    /*
+     * Prefix-less values get the `default` unit.
+     * The sign of negative values goes before or after the prefix: `-m1000` or `m-1000`.
+     */
+    pub fn with_default(s: &str, default: Unit) -> Result<Self, InputParseError> {
+        let (sign, s) = match s.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", s),
+        };
+        let mut chars = s.chars();
+        match chars.next() {
+            Some('0'..='9') => Ok(ParseResult {
+                unit: default,
+                ts: format!("{sign}{s}"),
             }),
-            _ => {
-                if let Ok(unit) = Unit::from_str(prefix) {
+            Some(prefix) => {
+                let rest = chars.as_str();
+                if !sign.is_empty() && rest.starts_with('-') {
+                    return Err(InputParseError);
+                }
+                if let Ok(unit) = Unit::from_str(&prefix.to_string()) {
                     Ok(ParseResult {
                         unit,
-                        ts: s.to_string()[1..].into(),
+                        ts: format!("{sign}{rest}"),
                     })
                 } else {
                     Err(InputParseError)
                 }
             }
+            None => Err(InputParseError),
         }
     }
 }
@@ -49,6 +73,47 @@ mod test {
         assert_eq!(res.ts, "1000".to_string());
     }
 
+    #[test]
+    fn test_from_str_empty() {
+        assert!(ParseResult::from_str("").is_err());
+    }
+
+    #[test]
+    fn test_from_str_multibyte_prefix() {
+        assert!(ParseResult::from_str("é1000").is_err());
+    }
+
+    #[test]
+    fn test_with_default_auto() {
+        let res = ParseResult::with_default("1000", Unit::Auto).unwrap();
+        assert_eq!(res.unit, Unit::Auto);
+
+        let res = ParseResult::with_default("m1000", Unit::Auto).unwrap();
+        assert_eq!(res.unit, Unit::Millis);
+
+        let res = ParseResult::from_str("a1000").unwrap();
+        assert_eq!(res.unit, Unit::Auto);
+        assert_eq!(res.ts, "1000".to_string());
+    }
+
+    #[test]
+    fn test_from_str_negative() {
+        for (s, unit) in [
+            ("-86400", Unit::Secs),
+            ("m-1000", Unit::Millis),
+            ("-m1000", Unit::Millis),
+        ] {
+            let res = ParseResult::from_str(s).unwrap();
+            assert_eq!(res.unit, unit);
+            assert_eq!(res.ts.trim_start_matches('-').len() + 1, res.ts.len());
+        }
+        assert_eq!(ParseResult::from_str("-86400").unwrap().ts, "-86400");
+        assert_eq!(ParseResult::from_str("-m1000").unwrap().ts, "-1000");
+        for s in ["-", "-m-1000", "-x1000"] {
+            assert!(ParseResult::from_str(s).is_err());
+        }
+    }
+
     #[test]
     fn test_from_str_prefix_seconds() {
         let s = "s1000";
@@ -0,0 +1,378 @@
+use chrono::{Datelike, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, TimeDelta};
+use std::str::FromStr;
+
+// Without rules, DST follows the current US ones.
+const DEFAULT_RULES: &str = "M3.2.0,M11.1.0";
+const DEFAULT_RULE_TIME: i32 = 2 * 3600;
+
+#[derive(Debug, PartialEq)]
+pub struct PosixParseError;
+
+#[derive(Debug, Clone, PartialEq)]
+enum RuleDay {
+    // Jn: 1 to 365, February 29th is never counted
+    Julian(u32),
+    // n: 0 to 365, February 29th is counted
+    Ordinal(u32),
+    // Mm.w.d: day d (0 is Sunday) of week w (5 is the last one) of month m
+    MonthWeekDay(u32, u32, u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    day: RuleDay,
+    // local time of the change, in seconds
+    time: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Dst {
+    abbr: String,
+    // seconds east of UTC
+    offset: i32,
+    start: Rule,
+    end: Rule,
+}
+
+/*
+ * A POSIX TZ string, like `EST5EDT,M3.2.0,M11.1.0` or `<+0530>-5:30`.
+ * Beware that POSIX offsets are positive west of Greenwich: `EST5` is UTC-5.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct PosixTz {
+    spec: String,
+    abbr: String,
+    // seconds east of UTC
+    offset: i32,
+    dst: Option<Dst>,
+}
+impl FromStr for PosixTz {
+    type Err = PosixParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rest = s;
+        let abbr = parse_abbr(&mut rest)?;
+        let offset = -parse_time(&mut rest, true)?;
+        if rest.is_empty() {
+            return Ok(PosixTz {
+                spec: s.to_string(),
+                abbr,
+                offset,
+                dst: None,
+            });
+        }
+
+        let dst_abbr = parse_abbr(&mut rest)?;
+        let dst_offset = if rest.is_empty() || rest.starts_with(',') {
+            offset + 3600
+        } else {
+            -parse_time(&mut rest, true)?
+        };
+        let rules = match rest.strip_prefix(',') {
+            Some(rules) => rules,
+            None if rest.is_empty() => DEFAULT_RULES,
+            None => return Err(PosixParseError),
+        };
+        let (start, end) = rules.split_once(',').ok_or(PosixParseError)?;
+        Ok(PosixTz {
+            spec: s.to_string(),
+            abbr,
+            offset,
+            dst: Some(Dst {
+                abbr: dst_abbr,
+                offset: dst_offset,
+                start: parse_rule(start)?,
+                end: parse_rule(end)?,
+            }),
+        })
+    }
+}
+impl PosixTz {
+    pub fn spec(&self) -> &str {
+        &self.spec
+    }
+
+    // Offset in effect at a UTC date time, with its abbreviation.
+    pub fn offset_at(&self, utc: &NaiveDateTime) -> (FixedOffset, &str) {
+        let std = (self.offset, self.abbr.as_str());
+        let Some(dst) = &self.dst else {
+            return fixed(std);
+        };
+        // out of chrono's range once shifted, at its very ends: standard time
+        let Some(local) = utc.checked_add_signed(TimeDelta::seconds(self.offset as i64)) else {
+            return fixed(std);
+        };
+        let year = local.year();
+        let (Some(start), Some(end)) = (
+            dst.start.utc(year, self.offset),
+            dst.end.utc(year, dst.offset),
+        ) else {
+            return fixed(std);
+        };
+        // southern hemisphere DST spans over the new year
+        let in_dst = if start < end {
+            start <= *utc && *utc < end
+        } else {
+            !(end <= *utc && *utc < start)
+        };
+        if in_dst {
+            fixed((dst.offset, dst.abbr.as_str()))
+        } else {
+            fixed(std)
+        }
+    }
+
+    // Both offsets are tried, those giving back the local time are kept.
+    pub fn offset_from_local(&self, local: &NaiveDateTime) -> LocalResult<(FixedOffset, &str)> {
+        let mut offsets = vec![self.offset];
+        if let Some(dst) = &self.dst {
+            offsets.push(dst.offset);
+        }
+        let mut found: Vec<(NaiveDateTime, (FixedOffset, &str))> = Vec::new();
+        for offset in offsets {
+            let Some(utc) = local.checked_sub_signed(TimeDelta::seconds(offset as i64)) else {
+                continue;
+            };
+            let (fix, abbr) = self.offset_at(&utc);
+            if fix.local_minus_utc() == offset && !found.iter().any(|(u, _)| *u == utc) {
+                found.push((utc, (fix, abbr)));
+            }
+        }
+        found.sort_by_key(|(utc, _)| *utc);
+        match found.len() {
+            0 => LocalResult::None,
+            1 => LocalResult::Single(found[0].1),
+            _ => LocalResult::Ambiguous(found[0].1, found[1].1),
+        }
+    }
+}
+
+impl Rule {
+    // UTC date time of the change in a year, `offset` being the one in effect before.
+    fn utc(&self, year: i32, offset: i32) -> Option<NaiveDateTime> {
+        let date = match self.day {
+            RuleDay::Julian(n) => {
+                let leap = NaiveDate::from_ymd_opt(year, 2, 29).is_some();
+                NaiveDate::from_yo_opt(year, if leap && n >= 60 { n + 1 } else { n })?
+            }
+            RuleDay::Ordinal(n) => NaiveDate::from_yo_opt(year, n + 1)?,
+            RuleDay::MonthWeekDay(m, w, d) => {
+                let first = NaiveDate::from_ymd_opt(year, m, 1)?;
+                let first_wd = first.weekday().num_days_from_sunday();
+                let mut day = 1 + (d + 7 - first_wd) % 7 + (w - 1) * 7;
+                while NaiveDate::from_ymd_opt(year, m, day).is_none() {
+                    day -= 7;
+                }
+                NaiveDate::from_ymd_opt(year, m, day)?
+            }
+        };
+        date.and_time(Default::default())
+            .checked_add_signed(TimeDelta::seconds(self.time as i64))?
+            .checked_sub_signed(TimeDelta::seconds(offset as i64))
+    }
+}
+
+fn fixed((offset, abbr): (i32, &str)) -> (FixedOffset, &str) {
+    (FixedOffset::east_opt(offset).unwrap(), abbr)
+}
+
+// Either 3+ letters, or anything between angle brackets: <+0530>.
+fn parse_abbr(rest: &mut &str) -> Result<String, PosixParseError> {
+    let (abbr, tail) = if let Some(quoted) = rest.strip_prefix('<') {
+        let end = quoted.find('>').ok_or(PosixParseError)?;
+        (&quoted[..end], &quoted[end + 1..])
+    } else {
+        let end = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        (&rest[..end], &rest[end..])
+    };
+    if abbr.len() < 3 {
+        return Err(PosixParseError);
+    }
+    *rest = tail;
+    Ok(abbr.to_string())
+}
+
+// [+-]hh[:mm[:ss]], in seconds; offsets are less than a day, rule times up to a week.
+fn parse_time(rest: &mut &str, is_offset: bool) -> Result<i32, PosixParseError> {
+    let (sign, tail) = match rest.as_bytes().first() {
+        Some(b'-') => (-1, &rest[1..]),
+        Some(b'+') => (1, &rest[1..]),
+        _ => (1, *rest),
+    };
+    let end = tail
+        .find(|c: char| !c.is_ascii_digit() && c != ':')
+        .unwrap_or(tail.len());
+    let mut secs = 0;
+    for (i, part) in tail[..end].split(':').enumerate() {
+        let max = match i {
+            0 if is_offset => 24,
+            0 => 167,
+            1 | 2 => 59,
+            _ => return Err(PosixParseError),
+        };
+        let n: i32 = part.parse().map_err(|_| PosixParseError)?;
+        if part.len() > 3 || n > max {
+            return Err(PosixParseError);
+        }
+        secs += n * [3600, 60, 1][i];
+    }
+    if is_offset && FixedOffset::east_opt(secs).is_none() {
+        return Err(PosixParseError);
+    }
+    *rest = &tail[end..];
+    Ok(sign * secs)
+}
+
+fn parse_rule(s: &str) -> Result<Rule, PosixParseError> {
+    let (day, time) = match s.split_once('/') {
+        Some((day, mut time)) => {
+            let secs = parse_time(&mut time, false)?;
+            if !time.is_empty() {
+                return Err(PosixParseError);
+            }
+            (day, secs)
+        }
+        None => (s, DEFAULT_RULE_TIME),
+    };
+    let number = |n: &str, min: u32, max: u32| -> Result<u32, PosixParseError> {
+        match n.parse::<u32>() {
+            Ok(n) if (min..=max).contains(&n) => Ok(n),
+            _ => Err(PosixParseError),
+        }
+    };
+    let day = if let Some(n) = day.strip_prefix('J') {
+        RuleDay::Julian(number(n, 1, 365)?)
+    } else if let Some(mwd) = day.strip_prefix('M') {
+        let mut mwd = mwd.split('.');
+        let (Some(m), Some(w), Some(d), None) = (mwd.next(), mwd.next(), mwd.next(), mwd.next())
+        else {
+            return Err(PosixParseError);
+        };
+        RuleDay::MonthWeekDay(number(m, 1, 12)?, number(w, 1, 5)?, number(d, 0, 6)?)
+    } else {
+        RuleDay::Ordinal(number(day, 0, 365)?)
+    };
+    Ok(Rule { day, time })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::posix::{PosixParseError, PosixTz};
+
+    use chrono::{DateTime, LocalResult, NaiveDate, NaiveDateTime};
+
+    fn utc(ts: i64) -> NaiveDateTime {
+        DateTime::from_timestamp(ts, 0).unwrap().naive_utc()
+    }
+
+    fn offset_at(tz: &PosixTz, ts: i64) -> (i32, String) {
+        let (fix, abbr) = tz.offset_at(&utc(ts));
+        (fix.local_minus_utc(), abbr.to_string())
+    }
+
+    #[test]
+    fn test_from_str_err() {
+        for s in [
+            "",
+            "E5",
+            "EST",
+            "EST+25",
+            "EST24",
+            "EST5EDT,M3.2.0",
+            "EST5EDT,M13.2.0,M11.1.0",
+            "EST5EDT,M3.2.7,M11.1.0",
+            "EST5EDT,J0,J365",
+            "EST5EDT,366,0",
+            "EST5EDT4x",
+            "<+05",
+            "EST5EDT,M3.2.0/x,M11.1.0",
+        ] {
+            assert_eq!(s.parse::<PosixTz>().unwrap_err(), PosixParseError, "{s}");
+        }
+    }
+
+    #[test]
+    fn test_no_dst() {
+        let tz: PosixTz = "<+0530>-5:30".parse().unwrap();
+        assert_eq!(offset_at(&tz, 1758643530), (19800, "+0530".to_string()));
+        let tz: PosixTz = "JST-9".parse().unwrap();
+        assert_eq!(offset_at(&tz, 1758643530), (32400, "JST".to_string()));
+    }
+
+    #[test]
+    fn test_us_rules() {
+        let tz: PosixTz = "EST5EDT,M3.2.0,M11.1.0".parse().unwrap();
+        // 2025-03-09T06:59:59Z, 2025-03-09T07:00:00Z
+        assert_eq!(offset_at(&tz, 1741503599), (-18000, "EST".to_string()));
+        assert_eq!(offset_at(&tz, 1741503600), (-14400, "EDT".to_string()));
+        // 2025-11-02T05:59:59Z, 2025-11-02T06:00:00Z
+        assert_eq!(offset_at(&tz, 1762063199), (-14400, "EDT".to_string()));
+        assert_eq!(offset_at(&tz, 1762063200), (-18000, "EST".to_string()));
+        // default rules and offset
+        let tz: PosixTz = "EST5EDT".parse().unwrap();
+        assert_eq!(offset_at(&tz, 1741503600), (-14400, "EDT".to_string()));
+        assert_eq!(offset_at(&tz, 1762063200), (-18000, "EST".to_string()));
+    }
+
+    #[test]
+    fn test_range_ends() {
+        let (min, max) = (NaiveDateTime::MIN, NaiveDateTime::MAX);
+        let tz: PosixTz = "<+14>-14XDT".parse().unwrap();
+        let (fix, abbr) = tz.offset_at(&max);
+        assert_eq!((fix.local_minus_utc(), abbr), (50400, "+14"));
+        assert!(matches!(tz.offset_from_local(&min), LocalResult::None));
+        let tz: PosixTz = "<-12>12XDT".parse().unwrap();
+        let (fix, abbr) = tz.offset_at(&min);
+        assert_eq!((fix.local_minus_utc(), abbr), (-43200, "-12"));
+        assert!(matches!(tz.offset_from_local(&max), LocalResult::None));
+    }
+
+    #[test]
+    fn test_southern_rules() {
+        // Australia/Sydney: from first Sunday of October to first Sunday of April
+        let tz: PosixTz = "AEST-10AEDT,M10.1.0,M4.1.0/3".parse().unwrap();
+        // 2025-01-15, 2025-06-15, 2025-12-15
+        assert_eq!(offset_at(&tz, 1736899200), (39600, "AEDT".to_string()));
+        assert_eq!(offset_at(&tz, 1749945600), (36000, "AEST".to_string()));
+        assert_eq!(offset_at(&tz, 1765756800), (39600, "AEDT".to_string()));
+    }
+
+    #[test]
+    fn test_julian_rules() {
+        let tz: PosixTz = "XST0XDT-1,J60/0,300".parse().unwrap();
+        // J60 is March 1st, even in leap years; 300 is October 27th in leap years
+        let (fix, _) = tz.offset_at(&NaiveDate::from_ymd_opt(2024, 2, 29).unwrap().into());
+        assert_eq!(fix.local_minus_utc(), 0);
+        let (fix, _) = tz.offset_at(&NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().into());
+        assert_eq!(fix.local_minus_utc(), 3600);
+        let day = NaiveDate::from_ymd_opt(2024, 10, 27).unwrap();
+        let (fix, _) = tz.offset_at(&day.and_hms_opt(0, 59, 0).unwrap());
+        assert_eq!(fix.local_minus_utc(), 3600);
+        let (fix, _) = tz.offset_at(&day.and_hms_opt(1, 0, 0).unwrap());
+        assert_eq!(fix.local_minus_utc(), 0);
+    }
+
+    #[test]
+    fn test_offset_from_local() {
+        let tz: PosixTz = "CET-1CEST,M3.5.0,M10.5.0/3".parse().unwrap();
+        let day = NaiveDate::from_ymd_opt(2025, 3, 30).unwrap();
+        let skipped = tz.offset_from_local(&day.and_hms_opt(2, 30, 0).unwrap());
+        assert_eq!(skipped, LocalResult::None);
+
+        let day = NaiveDate::from_ymd_opt(2025, 10, 26).unwrap();
+        let repeated = tz.offset_from_local(&day.and_hms_opt(2, 30, 0).unwrap());
+        let LocalResult::Ambiguous((first, "CEST"), (second, "CET")) = repeated else {
+            panic!("{repeated:?}");
+        };
+        assert_eq!(first.local_minus_utc(), 7200);
+        assert_eq!(second.local_minus_utc(), 3600);
+
+        let single = tz.offset_from_local(&day.and_hms_opt(12, 0, 0).unwrap());
+        let LocalResult::Single((fix, "CET")) = single else {
+            panic!("{single:?}");
+        };
+        assert_eq!(fix.local_minus_utc(), 3600);
+    }
+}
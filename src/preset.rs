@@ -0,0 +1,143 @@
+/*
+ * A named date format, selected by its name or one of its aliases.
+ * `utc` formats write the zone as text, so dates are always rendered in UTC.
+*/
+#[derive(Debug)]
+pub struct Preset {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub fmt: &'static str,
+    pub utc: bool,
+}
+
+// The seconds followed by the milliseconds, only right for instants after 1970.
+const EPOCH_MS: &str = "%s%3f";
+
+/*
+ * HTTP dates are always in GMT, whatever the `-T` timezone.
+ * `epoch-ms` is rendered as the signed count of milliseconds, see `in_millis`.
+*/
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "rfc2822",
+        aliases: &["rfc-2822"],
+        fmt: "%a, %d %b %Y %H:%M:%S %z",
+        utc: false,
+    },
+    Preset {
+        name: "rfc3339",
+        aliases: &["rfc-3339", "iso8601", "iso"],
+        fmt: "%Y-%m-%dT%H:%M:%S%:z",
+        utc: false,
+    },
+    Preset {
+        name: "iso-ms",
+        aliases: &[],
+        fmt: "%Y-%m-%dT%H:%M:%S%.3f%:z",
+        utc: false,
+    },
+    Preset {
+        name: "http",
+        aliases: &["http-date"],
+        fmt: "%a, %d %b %Y %H:%M:%S GMT",
+        utc: true,
+    },
+    Preset {
+        name: "syslog",
+        aliases: &[],
+        fmt: "%b %e %H:%M:%S",
+        utc: false,
+    },
+    Preset {
+        name: "sql",
+        aliases: &[],
+        fmt: "%Y-%m-%d %H:%M:%S",
+        utc: false,
+    },
+    Preset {
+        name: "date",
+        aliases: &[],
+        fmt: "%Y-%m-%d",
+        utc: false,
+    },
+    Preset {
+        name: "epoch-ms",
+        aliases: &[],
+        fmt: EPOCH_MS,
+        utc: false,
+    },
+];
+
+pub fn get_preset(name: &str) -> Option<&'static Preset> {
+    PRESETS
+        .iter()
+        .find(|x| x.name == name || x.aliases.contains(&name))
+}
+
+// Whether a format string is that of a preset only rendered in UTC.
+pub fn in_utc(fmt: &str) -> bool {
+    PRESETS.iter().any(|x| x.utc && x.fmt == fmt)
+}
+
+/*
+ * Whether a format string is that of `epoch-ms`, rendered from the milliseconds
+ * since the epoch: glued to the seconds, those of a negative instant with a
+ * fraction would be wrong (-0.5s would give -1500).
+*/
+pub fn in_millis(fmt: &str) -> bool {
+    fmt == EPOCH_MS
+}
+
+#[cfg(test)]
+mod test {
+    use crate::preset::{get_preset, in_millis, in_utc, PRESETS};
+    use crate::process::FmtDate;
+
+    use chrono::DateTime;
+    use chrono_tz::UTC;
+
+    #[test]
+    fn test_get_preset() {
+        assert_eq!(get_preset("rfc3339").unwrap().name, "rfc3339");
+        assert_eq!(get_preset("iso8601").unwrap().name, "rfc3339");
+        assert_eq!(get_preset("http-date").unwrap().name, "http");
+        assert!(get_preset("%Y-%m-%d").is_none());
+    }
+
+    #[test]
+    fn test_in_utc() {
+        assert!(in_utc(get_preset("http").unwrap().fmt));
+        assert!(!in_utc(get_preset("rfc2822").unwrap().fmt));
+        assert!(!in_utc("http"));
+    }
+
+    #[test]
+    fn test_presets() {
+        let dt = DateTime::from_timestamp(1758643530, 123_456_789).unwrap();
+        let expected = [
+            "Tue, 23 Sep 2025 16:05:30 +0000",
+            "2025-09-23T16:05:30+00:00",
+            "2025-09-23T16:05:30.123+00:00",
+            "Tue, 23 Sep 2025 16:05:30 GMT",
+            "Sep 23 16:05:30",
+            "2025-09-23 16:05:30",
+            "2025-09-23",
+            "1758643530123",
+        ];
+        for (preset, expected) in PRESETS.iter().zip(expected) {
+            let fmt_date = FmtDate::new(dt, preset.fmt.to_string(), UTC.into());
+            assert_eq!(fmt_date.to_string(), expected, "{}", preset.name);
+        }
+    }
+
+    #[test]
+    fn test_epoch_ms() {
+        let fmt = get_preset("epoch-ms").unwrap().fmt;
+        assert!(in_millis(fmt));
+        for (millis, expected) in [(-500, "-500"), (-1500, "-1500"), (-1, "-1"), (0, "0")] {
+            let dt = DateTime::from_timestamp_millis(millis).unwrap();
+            let fmt_date = FmtDate::new(dt, fmt.to_string(), UTC.into());
+            assert_eq!(fmt_date.to_string(), expected);
+        }
+    }
+}
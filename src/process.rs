@@ -1,89 +1,596 @@
+use chrono::format::{Item, StrftimeItems};
 use chrono::{DateTime, Utc};
-use chrono_tz::Tz;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::io;
 
-use crate::args::get_ts_strings;
-use crate::dump::DumpOutcomeFn;
+use crate::delta::Sequence;
+use crate::dump::Sink;
+use crate::expr;
+use crate::id;
 use crate::outcome::Outcome;
-use crate::value::ts_from_str;
-
-pub fn go(cli_args: Vec<String>, fmt: String, tz: Tz, dump_fn: DumpOutcomeFn) {
-    let mut outcomes: Vec<Outcome> = Vec::new();
-
-    match get_ts_strings(cli_args) {
-        Ok(ts_strs) => {
-            for ts_str in ts_strs.iter() {
-                let mut outcome = Outcome::new(ts_str.to_string());
-                match ts_from_str(ts_str.to_string()) {
-                    Ok(dt) => {
-                        let fmt_ts = FmtDate::new(dt, fmt.to_owned(), tz);
-                        outcome.set(format!("{fmt_ts}"));
-                    }
-                    Err(err) => {
-                        outcome.set(format!("{err}"));
-                    }
+use crate::preset;
+use crate::relative::Relative;
+use crate::select::Select;
+use crate::stats::Stats;
+use crate::status::Status;
+use crate::value::ValueOptions;
+use crate::zone::Zone;
+
+/*
+ * What's done besides rendering values: values are IDs with `ids`, the epoch of
+ * snowflakes; `select` tells which are kept; the others what outcomes get.
+*/
+#[derive(Debug, Default)]
+pub struct Extras {
+    pub ids: Option<DateTime<Utc>>,
+    pub select: Select,
+    pub relative: Option<Relative>,
+    pub delta: bool,
+    pub stats: bool,
+}
+
+/*
+ * Values are selected first, elapsed times and statistics only count the kept ones.
+ * With `delta`, the time elapsed since the previous and the first valid values
+ * is added to each outcome. With `stats`, a summary of the whole batch follows
+ * the outcomes, then that of `delta`, the largest gap: both go to the sink.
+ * Sorting reads every value before the first outcome is emitted.
+*/
+pub fn go(
+    ts_strs: impl Iterator<Item = String>,
+    fmt: String,
+    tzs: Vec<Zone>,
+    opts: ValueOptions,
+    extras: Extras,
+    strict: bool,
+    sink: &mut dyn Sink,
+) -> io::Result<Status> {
+    let mut status = Status::Converted;
+    let mut sequence = extras.delta.then(Sequence::default);
+    // expressions are computed in the first zone
+    let tz = tzs.first().cloned().unwrap_or(Zone::from(chrono_tz::UTC));
+    let mut stats = extras.stats.then(|| Stats::new(tz.clone()));
+    let select = &extras.select;
+    let mut seen: HashSet<DateTime<Utc>> = HashSet::new();
+    let results = ts_strs
+        .map(|ts_str| {
+            let result = match &extras.ids {
+                Some(epoch) => id::decode(&ts_str, epoch),
+                None => expr::eval(&ts_str, &opts, &tz),
+            };
+            (ts_str, result)
+        })
+        .filter(|(_, result)| match result {
+            Ok(parsed) => select.contains(&parsed.dt) && (!select.dedupe || seen.insert(parsed.dt)),
+            Err(_) => true,
+        });
+    // either streamed, or all read and sorted, failures last
+    let (streamed, mut sorted) = if select.sort {
+        (None, results.collect())
+    } else {
+        (Some(results), Vec::new())
+    };
+    sorted.sort_by_key(|(_, result)| (result.is_err(), result.as_ref().ok().map(|x| x.dt)));
+    sink.begin()?;
+    for (ts_str, result) in streamed.into_iter().flatten().chain(sorted) {
+        let mut outcome = Outcome::new(ts_str.to_string());
+        match result {
+            Ok(parsed) => {
+                let zones = tzs
+                    .iter()
+                    .map(|tz| {
+                        let fmt_ts = FmtDate::new(parsed.dt, fmt.to_owned(), tz.clone());
+                        (tz.to_string(), format!("{fmt_ts}"))
+                    })
+                    .collect();
+                outcome.set_zones(zones);
+                outcome.set_unit(parsed.unit, parsed.guessed);
+                outcome.set_instant(&parsed.dt);
+                if let Some(id) = parsed.id {
+                    outcome.set_id(id);
+                }
+                if let Some(relative) = &extras.relative {
+                    outcome.set_relative(relative.describe(&parsed.dt));
+                }
+                if let Some(sequence) = &mut sequence {
+                    outcome.set_step(&sequence.step(&ts_str, &parsed.dt));
+                }
+                if let Some(stats) = &mut stats {
+                    stats.add(&parsed.dt);
                 }
-                outcomes.push(outcome);
             }
-            dump_fn(outcomes);
-        }
-        Err(err) => {
-            println!("{err}");
+            Err(err) if strict => {
+                sink.finish()?;
+                eprintln!("invalid value '{ts_str}': {err}");
+                return Ok(Status::Failed);
+            }
+            Err(err) => {
+                if let Some(stats) = &mut stats {
+                    stats.add_error(&err);
+                }
+                outcome.set_error(&err);
+                status = Status::Failed;
+            }
         }
+        sink.emit(&outcome)?;
+    }
+    if let Some(stats) = stats {
+        let report = stats.report();
+        sink.summary(&report.to_string(), &serde_json::json!({ "stats": report }))?;
     }
+    if let Some(summary) = sequence.as_ref().and_then(Sequence::summary) {
+        sink.summary(
+            &summary.to_string(),
+            &serde_json::json!({ "delta": summary }),
+        )?;
+    }
+    sink.finish()?;
+    Ok(status)
 }
 
 #[derive(Debug)]
 pub struct FmtDate {
     dt: DateTime<Utc>,
     fmt: String,
-    tz: Tz,
+    tz: Zone,
 }
 impl std::fmt::Display for FmtDate {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if preset::in_millis(&self.fmt) {
+            return write!(f, "{}", self.dt.timestamp_millis());
+        }
         let dt = self.dt.with_timezone(&self.tz);
         write!(f, "{}", dt.format(self.fmt.as_str()))
     }
 }
 impl FmtDate {
-    pub fn new(dt: DateTime<Utc>, fmt: String, tz: Tz) -> FmtDate {
+    pub fn new(dt: DateTime<Utc>, fmt: String, tz: Zone) -> FmtDate {
         FmtDate { dt, fmt, tz }
     }
 }
 
+// An unknown or incomplete specifier, and its 1-based column in the format string.
+#[derive(Debug, PartialEq)]
+pub struct FmtError {
+    pub spec: String,
+    pub column: usize,
+}
+impl std::fmt::Display for FmtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid specifier '{}' at column {}",
+            self.spec, self.column
+        )
+    }
+}
+impl std::error::Error for FmtError {}
+
+/*
+ * Formatting a date with an invalid format string panics, so it's checked first.
+ * A specifier is the shortest run after a `%` that chrono reads without error
+ * (`%Y`, `%-d`, `%:z`, `%.3f`, `%T` standing for several items); there's none
+ * of more than 5 characters. Some are only meant for parsing (`%#z`) and fail
+ * once rendered, so each one is written for a fixed date too.
+*/
+pub fn check_fmt(fmt: &str) -> Result<(), FmtError> {
+    check_specs(fmt, true)
+}
+
+// Formats only parsing dates, as with --reverse, may have parse-only specifiers.
+pub fn check_parse_fmt(fmt: &str) -> Result<(), FmtError> {
+    check_specs(fmt, false)
+}
+
+fn check_specs(fmt: &str, rendered: bool) -> Result<(), FmtError> {
+    let is_spec = |spec: &str| {
+        let items: Vec<Item> = StrftimeItems::new(spec).collect();
+        !items.is_empty() && !items.contains(&Item::Error)
+    };
+    let renders = |spec: &str| {
+        let mut out = String::new();
+        write!(out, "{}", DateTime::UNIX_EPOCH.format(spec)).is_ok()
+    };
+    let mut chars = fmt.char_indices().enumerate();
+    while let Some((column, (start, c))) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        let ends: Vec<usize> = fmt[start..]
+            .char_indices()
+            .skip(2)
+            .map(|(idx, _)| start + idx)
+            .chain([fmt.len()])
+            .take(5)
+            .collect();
+        match ends.iter().find(|end| is_spec(&fmt[start..**end])) {
+            Some(end) if rendered && !renders(&fmt[start..*end]) => {
+                return Err(FmtError {
+                    spec: fmt[start..*end].to_string(),
+                    column: column + 1,
+                });
+            }
+            Some(end) => {
+                let len = fmt[start..*end].chars().count();
+                chars.nth(len - 2);
+            }
+            None => {
+                let spec: String = fmt[start..].chars().take(2).collect();
+                return Err(FmtError {
+                    spec,
+                    column: column + 1,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
+    use crate::dump::Sink;
     use crate::outcome::Outcome;
-    use crate::process::go;
     use crate::process::FmtDate;
+    use crate::process::{check_fmt, check_parse_fmt, go, Extras, FmtError};
+    use crate::relative::Relative;
+    use crate::select::Select;
+    use crate::status::Status;
+    use crate::unit::Unit;
+    use crate::value::{ValueError, ValueOptions};
 
     use chrono::{DateTime, Utc};
     use chrono_tz::UTC;
-    use std::sync::Mutex;
+
+    fn collect(values: &[&str], fmt: &str, extras: Extras) -> (Status, Vec<Outcome>) {
+        let mut collected: Vec<Outcome> = Vec::new();
+        let values = values.iter().map(|x| x.to_string());
+        let tzs = vec![UTC.into()];
+        let opts = ValueOptions::default();
+        let status = go(
+            values,
+            fmt.to_string(),
+            tzs,
+            opts,
+            extras,
+            false,
+            &mut collected,
+        );
+        (status.unwrap(), collected)
+    }
 
     #[test]
     fn test_go() {
-        static COLLECTED_OUTCOMES: Mutex<Vec<Outcome>> = Mutex::new(Vec::new());
+        let mut collected: Vec<Outcome> = Vec::new();
+        let status = go(
+            vec!["1337".to_string(), "errful".to_string()].into_iter(),
+            "%a, %d %b %Y %H:%M:%S %z".to_string(),
+            vec![UTC.into()],
+            ValueOptions::default(),
+            Extras::default(),
+            false,
+            &mut collected,
+        )
+        .unwrap();
 
-        fn test_dump_fn(outcomes: Vec<Outcome>) {
-            let mut collected = COLLECTED_OUTCOMES.lock().unwrap();
-            collected.extend(outcomes);
-        }
+        assert_eq!(
+            collected,
+            vec![
+                Outcome::new("1337".to_string())
+                    .set("Thu, 01 Jan 1970 00:22:17 +0000".to_string())
+                    .set_unit(Unit::Secs, false)
+                    .set_instant(&DateTime::from_timestamp(1337, 0).unwrap()),
+                Outcome::new("errful".to_string()).set_error(&ValueError::Nothing),
+            ]
+        );
+        assert_eq!(status, Status::Failed);
+    }
+
+    #[test]
+    fn test_go_strict() {
+        let mut collected: Vec<Outcome> = Vec::new();
+        let status = go(
+            vec!["1337".to_string(), "errful".to_string(), "42".to_string()].into_iter(),
+            "%Y-%m-%d".to_string(),
+            vec![UTC.into()],
+            ValueOptions::default(),
+            Extras::default(),
+            true,
+            &mut collected,
+        )
+        .unwrap();
 
+        assert_eq!(
+            collected,
+            vec![Outcome::new("1337".to_string())
+                .set("1970-01-01".to_string())
+                .set_unit(Unit::Secs, false)
+                .set_instant(&DateTime::from_timestamp(1337, 0).unwrap())]
+        );
+        assert_eq!(status, Status::Failed);
+    }
+
+    #[test]
+    fn test_go_auto() {
+        let mut collected: Vec<Outcome> = Vec::new();
         go(
-            vec!["tsp".to_string(), "1337".to_string(), "errful".to_string()],
-            "%a, %d %b %Y %H:%M:%S %z".to_string(),
-            UTC,
-            test_dump_fn,
+            vec!["1758643530123".to_string(), "s1337".to_string()].into_iter(),
+            "%Y-%m-%d".to_string(),
+            vec![UTC.into()],
+            ValueOptions {
+                auto: true,
+                ..Default::default()
+            },
+            Extras::default(),
+            false,
+            &mut collected,
+        )
+        .unwrap();
+
+        assert_eq!(
+            collected,
+            vec![
+                Outcome::new("1758643530123".to_string())
+                    .set("2025-09-23".to_string())
+                    .set_unit(Unit::Millis, true)
+                    .set_instant(&DateTime::from_timestamp_millis(1758643530123).unwrap()),
+                Outcome::new("s1337".to_string())
+                    .set("1970-01-01".to_string())
+                    .set_unit(Unit::Secs, false)
+                    .set_instant(&DateTime::from_timestamp(1337, 0).unwrap()),
+            ]
         );
+    }
+
+    #[test]
+    fn test_go_relative() {
+        let mut collected: Vec<Outcome> = Vec::new();
+        let reference = DateTime::from_timestamp(1758643530, 0);
+        go(
+            vec!["1758632730".to_string(), "errful".to_string()].into_iter(),
+            "%Y-%m-%d".to_string(),
+            vec![UTC.into()],
+            ValueOptions::default(),
+            Extras {
+                relative: Some(Relative::new(reference, false)),
+                ..Default::default()
+            },
+            false,
+            &mut collected,
+        )
+        .unwrap();
 
-        let collected = COLLECTED_OUTCOMES.lock().unwrap().to_owned();
         assert_eq!(
             collected,
             vec![
-                Outcome::new("1337".to_string()).set("Thu, 01 Jan 1970 00:22:17 +0000".to_string()),
-                Outcome::new("errful".to_string()).set("can't interpret the value".to_string()),
+                Outcome::new("1758632730".to_string())
+                    .set("2025-09-23".to_string())
+                    .set_unit(Unit::Secs, false)
+                    .set_instant(&DateTime::from_timestamp(1758632730, 0).unwrap())
+                    .set_relative("3 hours before".to_string()),
+                Outcome::new("errful".to_string()).set_error(&ValueError::Nothing),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_go_delta() {
+        let values = ["1758643530", "errful", "1758643590", "1758643560"];
+        let extras = Extras {
+            delta: true,
+            ..Default::default()
+        };
+        let (_, collected) = collect(&values, "%H:%M", extras);
+
+        let steps: Vec<(Option<i128>, Option<i128>, bool)> = collected
+            .iter()
+            .map(|x| (x.since_previous_ns(), x.since_first_ns(), x.out_of_order()))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (None, Some(0), false),
+                (None, None, false),
+                (Some(60_000_000_000), Some(60_000_000_000), false),
+                (Some(-30_000_000_000), Some(30_000_000_000), true),
+            ]
+        );
+    }
+
+    // The summaries given to a sink, outcomes being dropped.
+    #[derive(Default)]
+    struct Summaries(Vec<serde_json::Value>);
+    impl Sink for Summaries {
+        fn emit(&mut self, _outcome: &Outcome) -> std::io::Result<()> {
+            Ok(())
+        }
+
+        fn summary(&mut self, _text: &str, value: &serde_json::Value) -> std::io::Result<()> {
+            self.0.push(value.to_owned());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_go_summaries() {
+        let mut sink = Summaries::default();
+        let values = ["1758643530", "1758643590", "1758643560"].iter();
+        let extras = Extras {
+            delta: true,
+            stats: true,
+            ..Default::default()
+        };
+        let fmt = "%H:%M".to_string();
+        let opts = ValueOptions::default();
+        go(
+            values.map(|x| x.to_string()),
+            fmt,
+            vec![UTC.into()],
+            opts,
+            extras,
+            false,
+            &mut sink,
+        )
+        .unwrap();
+
+        let keys: Vec<&str> = sink
+            .0
+            .iter()
+            .filter_map(|x| x.as_object()?.keys().next().map(|x| x.as_str()))
+            .collect();
+        assert_eq!(keys, vec!["stats", "delta"]);
+        assert_eq!(sink.0[1]["delta"]["largest_gap_ns"], 60_000_000_000_i64);
+        assert_eq!(sink.0[1]["delta"]["out_of_order"], 1);
+    }
+
+    #[test]
+    fn test_go_select() {
+        let run = |select: Select| {
+            let values = [
+                "1758643590",
+                "errful",
+                "m1758643530000",
+                "1758643530",
+                "1758650000",
+            ];
+            let extras = Extras {
+                select,
+                delta: true,
+                ..Default::default()
+            };
+            let (_, collected) = collect(&values, "%H:%M", extras);
+            collected
+                .iter()
+                .map(|x| (x.value_in().to_string(), x.since_previous_ns()))
+                .collect::<Vec<(String, Option<i128>)>>()
+        };
+        let expected = |rows: &[(&str, Option<i128>)]| {
+            rows.iter()
+                .map(|(value, nanos)| (value.to_string(), nanos.map(|x| x * 1_000_000_000)))
+                .collect::<Vec<(String, Option<i128>)>>()
+        };
+
+        let select = Select {
+            sort: true,
+            dedupe: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            run(select),
+            expected(&[
+                ("m1758643530000", None),
+                ("1758643590", Some(60)),
+                ("1758650000", Some(6410)),
+                ("errful", None),
+            ])
+        );
+
+        let select = Select {
+            after: DateTime::from_timestamp(1758643530, 0),
+            before: DateTime::from_timestamp(1758650000, 0),
+            ..Default::default()
+        };
+        assert_eq!(
+            run(select),
+            expected(&[
+                ("1758643590", None),
+                ("errful", None),
+                ("m1758643530000", Some(-60)),
+                ("1758643530", Some(0)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_go_ids() {
+        let values = ["175928847299117063", "1758643530", "rubbish"];
+        let extras = Extras {
+            ids: crate::id::parse_epoch("discord").ok(),
+            ..Default::default()
+        };
+        let (status, collected) = collect(&values, "%Y-%m-%d %H:%M:%S", extras);
+
+        let summary: Vec<(&str, Option<&str>, Option<&str>)> = collected
+            .iter()
+            .map(|x| (x.value_out(), x.id().map(|id| id.kind), x.error()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("2016-04-30 11:18:25", Some("snowflake"), None),
+                ("2015-01-01 00:00:00", Some("snowflake"), None),
+                ("the value is not a supported ID", None, Some("not_an_id")),
             ]
         );
+        assert_eq!(status, Status::Failed);
+    }
+
+    #[test]
+    fn test_go_zones() {
+        let mut collected: Vec<Outcome> = Vec::new();
+        let status = go(
+            vec!["1758643530".to_string()].into_iter(),
+            "%H:%M".to_string(),
+            vec![
+                chrono_tz::Europe::Paris.into(),
+                chrono_tz::Asia::Tokyo.into(),
+            ],
+            ValueOptions::default(),
+            Extras::default(),
+            false,
+            &mut collected,
+        )
+        .unwrap();
+
+        assert_eq!(
+            collected,
+            vec![Outcome::new("1758643530".to_string())
+                .set_zones(vec![
+                    ("Europe/Paris".to_string(), "18:05".to_string()),
+                    ("Asia/Tokyo".to_string(), "01:05".to_string()),
+                ])
+                .set_unit(Unit::Secs, false)
+                .set_instant(&DateTime::from_timestamp(1758643530, 0).unwrap())]
+        );
+        assert_eq!(status, Status::Converted);
+    }
+
+    #[test]
+    fn test_check_fmt() {
+        for fmt in [
+            "",
+            "%Y-%m-%d",
+            "%a, %d %b %Y %H:%M:%S %z",
+            "100%% %-d/%_m %e",
+            "%H:%M:%S%.3f %:z %::z",
+            "%s%3f %.f %+",
+            "%T %D %F %R %c %x %X %r %v",
+            "é %Y ü",
+        ] {
+            assert_eq!(check_fmt(fmt), Ok(()), "{fmt}");
+        }
+    }
+
+    #[test]
+    fn test_check_fmt_err() {
+        let err = |spec: &str, column| {
+            Err(FmtError {
+                spec: spec.to_string(),
+                column,
+            })
+        };
+        assert_eq!(check_fmt("%Y-%Q"), err("%Q", 4));
+        assert_eq!(check_fmt("%N"), err("%N", 1));
+        assert_eq!(check_fmt("é %Y %"), err("%", 6));
+        assert_eq!(check_fmt("%H:%M %.9"), err("%.", 7));
+        assert_eq!(check_fmt("%%%"), err("%", 3));
+        assert_eq!(check_fmt("%H:%M %#z"), err("%#z", 7));
+        assert_eq!(check_parse_fmt("%H:%M %#z"), Ok(()));
+        assert_eq!(check_parse_fmt("%H:%M %Q"), err("%Q", 7));
+        assert_eq!(
+            check_fmt("%Y-%Q").unwrap_err().to_string(),
+            "invalid specifier '%Q' at column 4"
+        );
     }
 
     #[test]
@@ -92,17 +599,52 @@ mod test {
             DateTime::parse_from_str("2025-09-23 20:00:00 +00:00", "%Y-%m-%d %H:%M:%S %z")
                 .unwrap()
                 .into();
-        let fmt_date = FmtDate::new(dt, "%Y-%m-%d".to_string(), UTC);
+        let fmt_date = FmtDate::new(dt, "%Y-%m-%d".to_string(), UTC.into());
         assert_eq!("2025-09-23", format!("{}", fmt_date));
     }
 
+    #[test]
+    fn test_fmtdate_historical() {
+        let fmt = "%a, %d %b %Y %H:%M:%S %z".to_string();
+        let table = [
+            (-86400, "Wed, 31 Dec 1969 00:00:00 +0000"),
+            (-1, "Wed, 31 Dec 1969 23:59:59 +0000"),
+            (-14182940, "Sun, 20 Jul 1969 20:17:40 +0000"),
+            (-2208988800, "Mon, 01 Jan 1900 00:00:00 +0000"),
+        ];
+        for (ts, expected) in table {
+            let dt = DateTime::from_timestamp(ts, 0).unwrap();
+            let fmt_date = FmtDate::new(dt, fmt.to_owned(), UTC.into());
+            assert_eq!(format!("{fmt_date}"), expected);
+        }
+        // zone rules of the time apply: New York was on local mean time until 1883
+        let dt = DateTime::from_timestamp(-2840140800, 0).unwrap();
+        let fmt_date = FmtDate::new(
+            dt,
+            "%Y-%m-%d %H:%M:%S %:z".to_string(),
+            chrono_tz::America::New_York.into(),
+        );
+        assert_eq!(format!("{fmt_date}"), "1879-12-31 19:03:58 -04:56");
+    }
+
     #[test]
     fn test_fmtdate_implementation_with_tz() {
         let dt: DateTime<Utc> =
             DateTime::parse_from_str("2025-09-23 23:30:00 +00:00", "%Y-%m-%d %H:%M:%S %z")
                 .unwrap()
                 .into();
-        let fmt_date = FmtDate::new(dt, "%Y-%m-%d".to_string(), chrono_tz::Europe::Paris);
+        let fmt_date = FmtDate::new(dt, "%Y-%m-%d".to_string(), chrono_tz::Europe::Paris.into());
         assert_eq!("2025-09-24", format!("{}", fmt_date));
     }
+
+    #[test]
+    fn test_fmtdate_implementation_with_posix_tz() {
+        let dt: DateTime<Utc> =
+            DateTime::parse_from_str("2025-09-23 23:30:00 +00:00", "%Y-%m-%d %H:%M:%S %z")
+                .unwrap()
+                .into();
+        let tz = "EST5EDT,M3.2.0,M11.1.0".parse().unwrap();
+        let fmt_date = FmtDate::new(dt, "%Y-%m-%d %H:%M %Z".to_string(), tz);
+        assert_eq!("2025-09-23 19:30 EDT", format!("{}", fmt_date));
+    }
 }
@@ -0,0 +1,121 @@
+use chrono::{DateTime, TimeDelta, Utc};
+
+const UNITS: &[(i64, &str, &str)] = &[
+    (365 * 86400, "y", "year"),
+    (86400, "d", "day"),
+    (3600, "h", "hour"),
+    (60, "m", "minute"),
+    (1, "s", "second"),
+];
+
+/*
+ * Describes dates relatively to a reference, now by default:
+ * either with the largest unit only (`3 hours ago`) or in full (`3h 12m 5s ago`).
+ * A year is 365 days, sub-second differences are ignored.
+*/
+#[derive(Debug, Clone)]
+pub struct Relative {
+    reference: DateTime<Utc>,
+    now: bool,
+    full: bool,
+}
+impl Relative {
+    pub fn new(reference: Option<DateTime<Utc>>, full: bool) -> Relative {
+        Relative {
+            reference: reference.unwrap_or_else(Utc::now),
+            now: reference.is_none(),
+            full,
+        }
+    }
+
+    pub fn describe(&self, dt: &DateTime<Utc>) -> String {
+        let delta = *dt - self.reference;
+        let secs = delta.num_seconds().unsigned_abs() as i64;
+        if secs == 0 {
+            return if self.now { "now" } else { "same time" }.to_string();
+        }
+        let span = if self.full {
+            full_span(secs)
+        } else {
+            largest_span(secs)
+        };
+        match (delta < TimeDelta::zero(), self.now) {
+            (true, true) => format!("{span} ago"),
+            (false, true) => format!("in {span}"),
+            (true, false) => format!("{span} before"),
+            (false, false) => format!("{span} after"),
+        }
+    }
+}
+
+fn largest_span(secs: i64) -> String {
+    for (size, _, name) in UNITS.iter() {
+        let count = secs / size;
+        if count > 0 {
+            let plural = if count > 1 { "s" } else { "" };
+            return format!("{count} {name}{plural}");
+        }
+    }
+    String::new()
+}
+
+fn full_span(mut secs: i64) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for (size, short, _) in UNITS.iter() {
+        let count = secs / size;
+        secs %= size;
+        if count > 0 {
+            parts.push(format!("{count}{short}"));
+        }
+    }
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::relative::Relative;
+
+    use chrono::{DateTime, TimeDelta, Utc};
+
+    fn reference() -> DateTime<Utc> {
+        DateTime::from_timestamp(1758643530, 0).unwrap()
+    }
+
+    #[test]
+    fn test_describe_largest() {
+        let r = Relative::new(Some(reference()), false);
+        let table = [
+            (TimeDelta::zero(), "same time"),
+            (TimeDelta::milliseconds(-500), "same time"),
+            (TimeDelta::seconds(1), "1 second after"),
+            (TimeDelta::minutes(-90), "1 hour before"),
+            (
+                TimeDelta::hours(-3) - TimeDelta::minutes(59),
+                "3 hours before",
+            ),
+            (TimeDelta::days(2) + TimeDelta::hours(23), "2 days after"),
+            (TimeDelta::days(800), "2 years after"),
+        ];
+        for (delta, expected) in table {
+            assert_eq!(r.describe(&(reference() + delta)), expected);
+        }
+    }
+
+    #[test]
+    fn test_describe_full() {
+        let r = Relative::new(Some(reference()), true);
+        let delta = TimeDelta::days(1) + TimeDelta::hours(3) + TimeDelta::minutes(12);
+        assert_eq!(r.describe(&(reference() - delta)), "1d 3h 12m before");
+        let delta = TimeDelta::days(366) + TimeDelta::seconds(5);
+        assert_eq!(r.describe(&(reference() + delta)), "1y 1d 5s after");
+    }
+
+    #[test]
+    fn test_describe_now() {
+        let r = Relative::new(None, false);
+        let dt = Utc::now() - TimeDelta::hours(3) - TimeDelta::minutes(1);
+        assert_eq!(r.describe(&dt), "3 hours ago");
+        let dt = Utc::now() + TimeDelta::days(3);
+        assert_eq!(r.describe(&dt), "in 3 days");
+    }
+}
@@ -0,0 +1,181 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::io;
+
+use crate::dump::Sink;
+use crate::outcome::Outcome;
+use crate::status::Status;
+use crate::unit::Unit;
+use crate::value::ValueError;
+use crate::zone::Zone;
+
+/*
+ * RFC 3339 and RFC 2822 are always understood, then the date format is tried,
+ * with an offset first, and in the timezone when the date has none.
+*/
+pub fn dt_from_str(date_str: &str, fmt: &str, tz: &Zone) -> Result<DateTime<Utc>, ValueError> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(date_str) {
+        return Ok(dt.into());
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc2822(date_str) {
+        return Ok(dt.into());
+    }
+    if let Ok(dt) = DateTime::parse_from_str(date_str, fmt) {
+        return Ok(dt.into());
+    }
+    let naive = match NaiveDateTime::parse_from_str(date_str, fmt) {
+        Ok(naive) => naive,
+        Err(_) => match NaiveDate::parse_from_str(date_str, fmt) {
+            Ok(date) => date.and_time(Default::default()),
+            Err(_) => return Err(ValueError::NotADate),
+        },
+    };
+    // a local time skipped by a DST change doesn't exist, a repeated one is taken the first time
+    match tz.from_local_datetime(&naive).earliest() {
+        Some(dt) => Ok(dt.to_utc()),
+        None => Err(ValueError::NotATS),
+    }
+}
+
+pub fn go(
+    date_strs: impl Iterator<Item = String>,
+    fmt: String,
+    tz: Zone,
+    unit: Unit,
+    strict: bool,
+    sink: &mut dyn Sink,
+) -> io::Result<Status> {
+    let mut status = Status::Converted;
+    sink.begin()?;
+    for date_str in date_strs {
+        let date_str = date_str.trim();
+        if date_str.is_empty() {
+            continue;
+        }
+        let mut outcome = Outcome::new(date_str.to_string());
+        let epoch = dt_from_str(date_str, &fmt, &tz)
+            .and_then(|dt| Ok((dt, unit.to_epoch(&dt).ok_or(ValueError::NotATS)?)));
+        match epoch {
+            Ok((dt, ts)) => {
+                outcome.set(format!("{ts}"));
+                outcome.set_unit(unit, false);
+                outcome.set_instant(&dt);
+            }
+            Err(err) if strict => {
+                sink.finish()?;
+                eprintln!("invalid date '{date_str}': {err}");
+                return Ok(Status::Failed);
+            }
+            Err(err) => {
+                outcome.set_error(&err);
+                status = Status::Failed;
+            }
+        }
+        sink.emit(&outcome)?;
+    }
+    sink.finish()?;
+    Ok(status)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::outcome::Outcome;
+    use crate::reverse::{dt_from_str, go};
+    use crate::status::Status;
+    use crate::unit::Unit;
+    use crate::value::ValueError;
+
+    use chrono::DateTime;
+    use chrono_tz::UTC;
+
+    const DEFAULT_FMT: &str = "%a, %d %b %Y %H:%M:%S %z";
+
+    #[test]
+    fn test_dt_from_str_rfc() {
+        let expected = DateTime::from_timestamp(1758643530, 0).unwrap();
+        for date_str in [
+            "2025-09-23T18:05:30+02:00",
+            "2025-09-23T16:05:30Z",
+            "Tue, 23 Sep 2025 16:05:30 +0000",
+        ] {
+            assert_eq!(dt_from_str(date_str, "%Y", &UTC.into()).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_dt_from_str_fmt() {
+        let got = dt_from_str(
+            "23/09/2025 16:05:30.5 +0100",
+            "%d/%m/%Y %H:%M:%S%.f %z",
+            &UTC.into(),
+        );
+        assert_eq!(
+            got.unwrap(),
+            DateTime::from_timestamp(1758639930, 500_000_000).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_dt_from_str_fmt_in_tz() {
+        let fmt = "%Y-%m-%d %H:%M:%S";
+        let got = dt_from_str("2025-09-23 18:05:30", fmt, &chrono_tz::Europe::Paris.into());
+        assert_eq!(
+            got.unwrap(),
+            DateTime::from_timestamp(1758643530, 0).unwrap()
+        );
+
+        let got = dt_from_str("2025-09-23", "%Y-%m-%d", &chrono_tz::Europe::Paris.into());
+        assert_eq!(
+            got.unwrap(),
+            DateTime::from_timestamp(1758578400, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_dt_from_str_dst() {
+        let fmt = "%Y-%m-%d %H:%M";
+        let paris = &chrono_tz::Europe::Paris.into();
+        // skipped
+        let error = dt_from_str("2025-03-30 02:30", fmt, paris).unwrap_err();
+        assert_eq!(error, ValueError::NotATS);
+        // repeated
+        let got = dt_from_str("2025-10-26 02:30", fmt, paris).unwrap();
+        assert_eq!(got, DateTime::from_timestamp(1761438600, 0).unwrap());
+    }
+
+    #[test]
+    fn test_dt_from_str_err() {
+        let error = dt_from_str("yesterday", DEFAULT_FMT, &UTC.into()).unwrap_err();
+        assert_eq!(error, ValueError::NotADate);
+    }
+
+    #[test]
+    fn test_go() {
+        let mut collected: Vec<Outcome> = Vec::new();
+        let status = go(
+            vec![
+                " 2025-09-23T16:05:30.123Z".to_string(),
+                "".to_string(),
+                "nope".to_string(),
+            ]
+            .into_iter(),
+            DEFAULT_FMT.to_string(),
+            UTC.into(),
+            Unit::Millis,
+            false,
+            &mut collected,
+        )
+        .unwrap();
+
+        assert_eq!(
+            collected,
+            vec![
+                Outcome::new("2025-09-23T16:05:30.123Z".to_string())
+                    .set("1758643530123".to_string())
+                    .set_unit(Unit::Millis, false)
+                    .set_instant(&DateTime::from_timestamp_millis(1758643530123).unwrap()),
+                Outcome::new("nope".to_string()).set_error(&ValueError::NotADate),
+            ]
+        );
+        assert_eq!(status, Status::Failed);
+    }
+}
@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+
+/*
+ * Which converted values are kept, and in which order: instants from `after`,
+ * included, up to `before`, excluded, the first of equal instants with `dedupe`,
+ * chronologically with `sort`. Failed values are always kept, after the others
+ * when sorting.
+*/
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Select {
+    pub after: Option<DateTime<Utc>>,
+    pub before: Option<DateTime<Utc>>,
+    pub sort: bool,
+    pub dedupe: bool,
+}
+impl Select {
+    pub fn contains(&self, dt: &DateTime<Utc>) -> bool {
+        self.after.is_none_or(|after| after <= *dt) && self.before.is_none_or(|before| *dt < before)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::select::Select;
+
+    use chrono::DateTime;
+
+    #[test]
+    fn test_contains() {
+        let dt = |secs| DateTime::from_timestamp(secs, 0).unwrap();
+        let select = Select {
+            after: Some(dt(10)),
+            before: Some(dt(20)),
+            ..Default::default()
+        };
+        assert!(!select.contains(&dt(9)));
+        assert!(select.contains(&dt(10)));
+        assert!(select.contains(&dt(19)));
+        assert!(!select.contains(&dt(20)));
+        assert!(Select::default().contains(&dt(-1)));
+    }
+}
@@ -0,0 +1,250 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::diff::{epoch_ns, human};
+use crate::value::ValueError;
+use crate::zone::Zone;
+
+const DAY_NANOS: i128 = 86_400_000_000_000;
+// Hourly buckets up to two days, daily ones beyond.
+const HOURLY_SPAN: i128 = 2 * DAY_NANOS;
+const BAR_WIDTH: usize = 40;
+
+/*
+ * What a batch of values amounts to, gathered while they're processed:
+ * failures by code, and every instant, kept for the gaps between them.
+*/
+#[derive(Debug)]
+pub struct Stats {
+    tz: Zone,
+    count: usize,
+    failures: BTreeMap<&'static str, usize>,
+    instants: Vec<DateTime<Utc>>,
+}
+impl Stats {
+    pub fn new(tz: Zone) -> Stats {
+        Stats {
+            tz,
+            count: 0,
+            failures: BTreeMap::new(),
+            instants: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, dt: &DateTime<Utc>) {
+        self.count += 1;
+        self.instants.push(*dt);
+    }
+
+    pub fn add_error(&mut self, err: &ValueError) {
+        self.count += 1;
+        *self.failures.entry(err.code()).or_default() += 1;
+    }
+
+    pub fn report(&self) -> Report {
+        let mut instants = self.instants.to_owned();
+        instants.sort();
+        let render = |dt: &DateTime<Utc>| {
+            dt.with_timezone(&self.tz)
+                .to_rfc3339_opts(SecondsFormat::AutoSi, true)
+        };
+        let (first, last) = (instants.first(), instants.last());
+        let span_ns = first.zip(last).map(|(a, b)| epoch_ns(b) - epoch_ns(a));
+        let mut gaps: Vec<i128> = instants
+            .windows(2)
+            .map(|x| epoch_ns(&x[1]) - epoch_ns(&x[0]))
+            .collect();
+        gaps.sort();
+        Report {
+            count: self.count,
+            converted: instants.len(),
+            failed: self.count - instants.len(),
+            failures: self.failures.to_owned(),
+            first: first.map(render),
+            last: last.map(render),
+            span_ns,
+            gaps: Gaps::new(&gaps),
+            histogram: span_ns.map(|span| Histogram::new(&instants, &self.tz, span)),
+        }
+    }
+}
+
+// Percentiles of the gaps between sorted instants, by nearest rank.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Gaps {
+    median_ns: i128,
+    p90_ns: i128,
+    p99_ns: i128,
+    max_ns: i128,
+}
+impl Gaps {
+    fn new(sorted: &[i128]) -> Option<Gaps> {
+        let percentile = |p: usize| sorted[(p * sorted.len()).div_ceil(100).max(1) - 1];
+        let max_ns = *sorted.last()?;
+        Some(Gaps {
+            median_ns: percentile(50),
+            p90_ns: percentile(90),
+            p99_ns: percentile(99),
+            max_ns,
+        })
+    }
+}
+
+// Counts per local hour, or per local day, empty buckets left out.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Histogram {
+    bucket: &'static str,
+    zone: String,
+    counts: BTreeMap<String, usize>,
+}
+impl Histogram {
+    fn new(instants: &[DateTime<Utc>], tz: &Zone, span: i128) -> Histogram {
+        let (bucket, fmt) = if span <= HOURLY_SPAN {
+            ("hour", "%Y-%m-%d %H:00")
+        } else {
+            ("day", "%Y-%m-%d")
+        };
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for dt in instants {
+            let key = dt.with_timezone(tz).format(fmt).to_string();
+            *counts.entry(key).or_default() += 1;
+        }
+        Histogram {
+            bucket,
+            zone: tz.to_string(),
+            counts,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Report {
+    count: usize,
+    converted: usize,
+    failed: usize,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    failures: BTreeMap<&'static str, usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    span_ns: Option<i128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gaps: Option<Gaps>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    histogram: Option<Histogram>,
+}
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "count: {}, converted: {}, failed: {}",
+            self.count, self.converted, self.failed
+        )?;
+        if !self.failures.is_empty() {
+            let failures: Vec<String> = self
+                .failures
+                .iter()
+                .map(|(code, count)| format!("{code}: {count}"))
+                .collect();
+            write!(f, " ({})", failures.join(", "))?;
+        }
+        if let (Some(first), Some(last), Some(span)) = (&self.first, &self.last, self.span_ns) {
+            write!(f, "\nfirst: {first}, last: {last}, span: {}", human(span))?;
+        }
+        if let Some(gaps) = &self.gaps {
+            write!(
+                f,
+                "\ngaps: median {}, p90 {}, p99 {}, max {}",
+                human(gaps.median_ns),
+                human(gaps.p90_ns),
+                human(gaps.p99_ns),
+                human(gaps.max_ns)
+            )?;
+        }
+        if let Some(histogram) = &self.histogram {
+            write!(f, "\nper {} in {}:", histogram.bucket, histogram.zone)?;
+            let most = histogram.counts.values().max().copied().unwrap_or(1);
+            for (key, count) in histogram.counts.iter() {
+                let bar = "#".repeat((count * BAR_WIDTH).div_ceil(most));
+                write!(f, "\n  {key:16}  {count:>6}  {bar}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::stats::{Gaps, Stats};
+    use crate::value::ValueError;
+
+    use chrono::DateTime;
+
+    fn stats(secs: &[i64], tz: &str) -> Stats {
+        let mut stats = Stats::new(tz.parse().unwrap());
+        for secs in secs {
+            stats.add(&DateTime::from_timestamp(*secs, 0).unwrap());
+        }
+        stats
+    }
+
+    #[test]
+    fn test_gaps() {
+        let sorted: Vec<i128> = (1..=10).collect();
+        let gaps = Gaps::new(&sorted).unwrap();
+        assert_eq!(
+            gaps,
+            Gaps {
+                median_ns: 5,
+                p90_ns: 9,
+                p99_ns: 10,
+                max_ns: 10
+            }
+        );
+        assert_eq!(Gaps::new(&[7]).unwrap().median_ns, 7);
+        assert_eq!(Gaps::new(&[]), None);
+    }
+
+    #[test]
+    fn test_report() {
+        let mut stats = stats(&[1758643590, 1758643530, 1758650000, 1758643560], "UTC");
+        stats.add_error(&ValueError::NotAnInt);
+        stats.add_error(&ValueError::NotAnInt);
+        stats.add_error(&ValueError::NotATS);
+        assert_eq!(
+            stats.report().to_string(),
+            "count: 7, converted: 4, failed: 3 (not_a_timestamp: 1, not_an_integer: 2)\n\
+             first: 2025-09-23T16:05:30Z, last: 2025-09-23T17:53:20Z, span: 1h 47m 50s\n\
+             gaps: median 30s, p90 1h 46m 50s, p99 1h 46m 50s, max 1h 46m 50s\n\
+             per hour in UTC:\n  \
+             2025-09-23 16:00       3  ########################################\n  \
+             2025-09-23 17:00       1  ##############"
+        );
+    }
+
+    #[test]
+    fn test_report_json() {
+        let report = stats(&[1758643530, 1758816330], "Europe/Paris").report();
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            r#"{"count":2,"converted":2,"failed":0,"first":"2025-09-23T18:05:30+02:00","last":"2025-09-25T18:05:30+02:00","span_ns":172800000000000,"gaps":{"median_ns":172800000000000,"p90_ns":172800000000000,"p99_ns":172800000000000,"max_ns":172800000000000},"histogram":{"bucket":"hour","zone":"Europe/Paris","counts":{"2025-09-23 18:00":1,"2025-09-25 18:00":1}}}"#
+        );
+        let report = stats(&[1758643530, 1758816331], "Europe/Paris").report();
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["histogram"]["bucket"], "day");
+        assert_eq!(json["histogram"]["counts"]["2025-09-25"], 1);
+    }
+
+    #[test]
+    fn test_report_empty() {
+        let report = Stats::new("UTC".parse().unwrap()).report();
+        assert_eq!(report.to_string(), "count: 0, converted: 0, failed: 0");
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            r#"{"count":0,"converted":0,"failed":0}"#
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use std::process::ExitCode;
+
+/*
+ * How a run went, from best to worst, turned into the exit code:
+ * 0 when all values converted, 1 when some failed, 2 on usage errors.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Converted,
+    Failed,
+    Usage,
+}
+impl Status {
+    pub fn code(self) -> u8 {
+        match self {
+            Status::Converted => 0,
+            Status::Failed => 1,
+            Status::Usage => 2,
+        }
+    }
+}
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> ExitCode {
+        ExitCode::from(status.code())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::status::Status;
+
+    #[test]
+    fn test_code() {
+        assert_eq!(Status::Converted.code(), 0);
+        assert_eq!(Status::Failed.code(), 1);
+        assert_eq!(Status::Usage.code(), 2);
+    }
+
+    #[test]
+    fn test_worst() {
+        assert_eq!(Status::Converted.max(Status::Failed), Status::Failed);
+        assert_eq!(Status::Usage.max(Status::Failed), Status::Usage);
+    }
+}
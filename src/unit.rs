@@ -1,5 +1,6 @@
 use chrono::DateTime;
 use chrono::Utc;
+use serde::Serialize;
 use std::str::FromStr;
 
 #[derive(Debug, PartialEq)]
@@ -7,12 +8,15 @@ pub struct UnitParseError;
 
 type ParseFn = fn(i64) -> Option<DateTime<Utc>>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum Unit {
     Secs,
     Millis,
     Micros,
     Nanos,
+    // To be guessed from the magnitude of the value, see `Window::guess_unit`.
+    Auto,
 }
 impl FromStr for Unit {
     type Err = UnitParseError;
@@ -23,11 +27,24 @@ impl FromStr for Unit {
             "m" => Ok(Unit::Millis),
             "u" => Ok(Unit::Micros),
             "n" => Ok(Unit::Nanos),
+            "a" => Ok(Unit::Auto),
             _ => Err(UnitParseError),
         }
     }
 }
+impl std::fmt::Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Unit::Secs => write!(f, "secs"),
+            Unit::Millis => write!(f, "millis"),
+            Unit::Micros => write!(f, "micros"),
+            Unit::Nanos => write!(f, "nanos"),
+            Unit::Auto => write!(f, "auto"),
+        }
+    }
+}
 impl Unit {
+    // `Unit::Auto` is expected to be resolved beforehand, it falls back to seconds.
     pub fn get_parser(&self) -> ParseFn {
         match self {
             Unit::Millis => DateTime::from_timestamp_millis,
@@ -36,6 +53,27 @@ impl Unit {
             _ => DateTime::from_timestamp_secs,
         }
     }
+
+    // Like `get_parser`, `Unit::Auto` falls back to seconds.
+    pub fn nanos(self) -> i128 {
+        match self {
+            Unit::Millis => 1_000_000,
+            Unit::Micros => 1_000,
+            Unit::Nanos => 1,
+            _ => 1_000_000_000,
+        }
+    }
+
+    // None when the date doesn't fit in the unit (nanos only cover 1677 to 2262).
+    pub fn to_epoch(self, dt: &DateTime<Utc>) -> Option<i64> {
+        match self {
+            Unit::Secs => Some(dt.timestamp()),
+            Unit::Millis => Some(dt.timestamp_millis()),
+            Unit::Micros => Some(dt.timestamp_micros()),
+            Unit::Nanos => dt.timestamp_nanos_opt(),
+            Unit::Auto => None,
+        }
+    }
 }
 
 // Wrapper to match the Option<DateTime<Utc>> signature of other timestamp parsers.
@@ -79,6 +117,12 @@ mod test_from_str {
         assert_eq!(u, Unit::Nanos);
     }
 
+    #[test]
+    fn test_from_str_auto() {
+        let u = Unit::from_str("a").unwrap();
+        assert_eq!(u, Unit::Auto);
+    }
+
     #[test]
     fn test_from_str_err() {
         let error = Unit::from_str("x").unwrap_err();
@@ -108,3 +152,22 @@ mod test_parser {
         }
     }
 }
+
+#[cfg(test)]
+mod test_to_epoch {
+    use crate::unit::Unit;
+    use chrono::DateTime;
+
+    #[test]
+    fn test_to_epoch() {
+        let dt = DateTime::from_timestamp(1758643530, 123456789).unwrap();
+        assert_eq!(Unit::Secs.to_epoch(&dt), Some(1758643530));
+        assert_eq!(Unit::Millis.to_epoch(&dt), Some(1758643530123));
+        assert_eq!(Unit::Micros.to_epoch(&dt), Some(1758643530123456));
+        assert_eq!(Unit::Nanos.to_epoch(&dt), Some(1758643530123456789));
+        assert_eq!(Unit::Auto.to_epoch(&dt), None);
+
+        let far = DateTime::from_timestamp(100000000000, 0).unwrap();
+        assert_eq!(Unit::Nanos.to_epoch(&far), None);
+    }
+}
@@ -8,19 +8,66 @@ pub fn print_usage() {
 tsp is a timestamp parser.
 
 \x1b[1mUSAGE\x1b[22m: tsp [OPTIONS] [timestamps]...
+       tsp diff [OPTIONS] [timestamps]...
 
 \x1b[1mOPTIONS\x1b[22m:
-  -F <FORMAT>       Date format string  (default: RFC 2822)
-  -T <TZ>           Date timezone       (default: UTC)
-  -j, --json        JSON output
-  -h, --help        Print help
+  -F, --format <FORMAT>  Date format string or preset  (default: rfc2822)
+  -T, --tz <TZ>[,<TZ>]   Date timezones      (default: UTC, repeatable)
+  --lenient-tz           Use the default timezone instead of failing on unknown ones
+  -f, --file <FILE>      Read timestamps from FILE, '-' for stdin (repeatable)
+  -j, --json             JSON output, same as --output json
+  --output <OUTPUT>      Output format  (default: text)
+  --strict               Stop at the first invalid value, reported on stderr,
+                         and fail on an invalid format instead of using the default
+  --filter               Rewrite timestamps found in input lines, leave the rest untouched
+  --annotate             Like --filter, but keep the timestamps and append their date
+  --auto                 Guess the granularity of values without prefix
+  --window <Y..Y>        Plausible years of guessed timestamps  (default: 2000..2100)
+  --relative             Add how long ago, or in how long, dates are
+  --relative-full        Like --relative, with all units: 1d 3h 12m 5s ago
+  --ref <VALUE>          Reference timestamp of --relative*  (default: now)
+  --sort                 Sort values chronologically, failed ones last
+  --dedupe               Drop values giving the same instant as a previous one
+  --after <VALUE>        Only keep dates from VALUE, included
+  --before <VALUE>       Only keep dates before VALUE, excluded
+  --delta                Add the time elapsed since the previous and the first values,
+                         flag values out of order and report the largest gap on stderr
+  --stats                Follow the outcomes with a summary: failures, first and last dates,
+                         gaps percentiles and a histogram per hour or day in the first TZ
+  --id                   Values are IDs: snowflakes, UUIDs v1/v6/v7, ULIDs, KSUIDs or
+                         ObjectIds, converted from the timestamp they embed
+  --epoch <EPOCH>        Epoch of snowflakes, twitter, discord or a value, implies --id
+                         (default: twitter)
+  -r, --reverse          Convert dates to timestamps
+  -U, --unit <UNIT>      Timestamps unit of --reverse, s, m, u or n  (default: s)
+  --show-config          Print the effective settings and where they come from
+  --list-formats         Print the format presets and aliases, with now as a sample
+  --pairs                Durations of diff between the values of each pair, not consecutive ones
+  -h, --help             Print help
 
-  FORMAT supports https://docs.rs/chrono/{chrono}/chrono/format/strftime/index.html
-  TZ     supports https://docs.rs/chrono-tz/{chrono_tz}/chrono_tz/enum.Tz.html
+  Values go after a space or '=' (--tz=UTC), short flags combine (-jr).
+  '--' ends the options, negative values (-86400, -m1000) are timestamps.
+
+  OUTPUT is one of text, json, ndjson, csv, tsv, yaml or markdown
+  FORMAT is a preset: rfc2822, rfc3339 (iso8601), iso-ms, http (in UTC), syslog, sql,
+         date, epoch-ms, an alias from the configuration, or a strftime string,
+         see https://docs.rs/chrono/{chrono}/chrono/format/strftime/index.html
+  TZ     supports https://docs.rs/chrono-tz/{chrono_tz}/chrono_tz/enum.Tz.html,
+         fixed offsets (+05:30, UTC-8), POSIX TZ strings (EST5EDT,M3.2.0,M11.1.0)
+         and 'local' for the system timezone
+
+\x1b[1mCONFIGURATION\x1b[22m:
+  Defaults of --format, --tz and --output are read from $XDG_CONFIG_HOME/tsp/config.toml
+  (~/.config/tsp/config.toml), as 'format', 'tz' and 'output' keys, then from the
+  TSP_FORMAT, TSP_TZ and TSP_OUTPUT environment variables. Options take precedence.
+  A [formats] table defines format aliases: short = \"%H:%M\"
 
 \x1b[1mARGS\x1b[22m:
   <timestamps>...        timestamps to convert
 
+Without timestamps nor files, values are read from stdin, as with a '-' value.
+Values read from files or stdin are whitespace separated.
+
 
 Timestamps granularity is either in seconds, milliseconds, microseconds or nanoseconds.
 The parser supports a value prefix for each granularity level:
@@ -28,56 +75,38 @@ The parser supports a value prefix for each granularity level:
   * m  =>  milliseconds
   * u  =>  microseconds
   * n  =>  nanoseconds
-If a value has no prefix, it is parsed as seconds."
-    );
-}
+  * a  =>  guessed from the value magnitude
+If a value has no prefix, it is parsed as seconds, or guessed with --auto.
+Values may have a decimal fraction, exact down to the nanosecond.
+Negative values are dates before 1970, the sign goes before or after the prefix:
+-86400, -m1000 or m-1000.
+A guessed granularity is the coarsest one which gives a date in the window.
 
-pub fn is_help(args: Vec<String>) -> bool {
-    for v in ["-h", "--help"]
-        .iter()
-        .map(|s| s.to_string())
-        .collect::<Vec<String>>()
-    {
-        if args.iter().any(|s| s == &v) {
-            return true;
-        }
-    }
-    false
-}
+Values, and --ref, can be expressions: a value or 'now', then durations to add or
+subtract, like 1758643530+1h30m, now-7d or 'm1758643530 - 250ms' (quoted with spaces).
+Durations units are y, mo, w, d, h, m, s, ms, us and ns. Years, months, weeks and days
+follow the calendar of the first timezone, keeping the local time across DST changes.
 
-#[cfg(test)]
-mod test {
-    use crate::usage::is_help;
+The diff subcommand gives the signed duration from each value to the next one, or
+within pairs with --pairs: in days, hours, minutes and seconds, as an ISO 8601 duration
+(PT1H2M3.5S) and as seconds, milliseconds and nanoseconds in machine readable outputs.
 
-    #[test]
-    fn test_is_help_true_short() {
-        let some_args: Vec<String> = vec!["tsp", "1758642010", "--help"]
-            .iter()
-            .map(|a| a.to_string())
-            .collect();
-        let h = is_help(some_args);
-        assert_eq!(h, true);
-    }
+Options a mode doesn't use are usage errors, like --pairs outside diff or -U without -r.
 
-    #[test]
-    fn test_is_help_true_long() {
-        let some_args = vec!["tsp", "1758642010", "-h"]
-            .iter()
-            .map(|a| a.to_string())
-            .collect();
-        let h = is_help(some_args);
-        assert_eq!(h, true);
-    }
+In filter mode, each input line is printed back with integers that look like
+timestamps replaced by their date, their granularity being guessed.
 
-    #[test]
-    fn test_is_help_false() {
-        let some_args = vec!["tsp", "1758642010"]
-            .iter()
-            .map(|a| a.to_string())
-            .collect();
-        let h = is_help(some_args);
-        assert_eq!(h, false);
-    }
+With several timezones, dates are rendered in each of them, in extra columns.
+Filter and reverse modes only use the first one.
+Fixed offsets count east of UTC: UTC-8 is 8 hours behind UTC.
+POSIX TZ strings count west of UTC: EST5 is 5 hours behind UTC.
+
+The exit code is 0 when all values converted, 1 when some failed,
+2 on usage errors such as an unknown timezone or an unreadable file.
+
+In reverse mode, each input line is a date, read as RFC 3339, RFC 2822 or FORMAT.
+Dates without offset are read in TZ."
+    );
 }
 
 #[cfg(test)]
@@ -1,13 +1,19 @@
 use chrono::{DateTime, Utc};
 use std::str::FromStr;
 
-use crate::parse::ParseResult;
+use crate::id::IdParts;
+use crate::parse::{InputParseError, ParseResult};
+use crate::unit::Unit;
+use crate::window::Window;
 
 #[derive(Debug, PartialEq)]
 pub enum ValueError {
     NotAnInt,
     NotATS,
     Nothing,
+    NotADate,
+    NotAnExpr,
+    NotAnId,
 }
 impl std::fmt::Display for ValueError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -15,30 +21,149 @@ impl std::fmt::Display for ValueError {
             ValueError::NotAnInt => write!(f, "the value is not an integer"),
             ValueError::NotATS => write!(f, "the value is not a timestamp"),
             ValueError::Nothing => write!(f, "can't interpret the value"),
+            ValueError::NotADate => write!(f, "the value is not a date"),
+            ValueError::NotAnExpr => write!(f, "the expression is invalid"),
+            ValueError::NotAnId => write!(f, "the value is not a supported ID"),
         }
     }
 }
+impl std::error::Error for ValueError {}
+impl ValueError {
+    // Stable identifiers for machine readable outputs, unlike the messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ValueError::NotAnInt => "not_an_integer",
+            ValueError::NotATS => "not_a_timestamp",
+            ValueError::Nothing => "invalid_input",
+            ValueError::NotADate => "not_a_date",
+            ValueError::NotAnExpr => "invalid_expression",
+            ValueError::NotAnId => "not_an_id",
+        }
+    }
+}
+impl From<InputParseError> for ValueError {
+    fn from(_: InputParseError) -> ValueError {
+        ValueError::Nothing
+    }
+}
 
-pub fn ts_from_str(ts_str: String) -> Result<DateTime<Utc>, ValueError> {
-    if let Ok(input) = ParseResult::from_str(&ts_str) {
-        if let Ok(ts) = input.ts.parse() {
-            let parse_fn = input.unit.get_parser();
-            if let Some(dt) = parse_fn(ts) {
-                Ok(dt)
-            } else {
-                Err(ValueError::NotATS)
-            }
+/*
+ * How values are read: `auto` makes prefix-less values guessed against `window`,
+ * `now` is the instant expressions like `now-7d` start from, the same for a whole run.
+*/
+#[derive(Debug, Clone)]
+pub struct ValueOptions {
+    pub auto: bool,
+    pub window: Window,
+    pub now: DateTime<Utc>,
+}
+impl Default for ValueOptions {
+    fn default() -> Self {
+        ValueOptions {
+            auto: false,
+            window: Window::default(),
+            now: Utc::now(),
+        }
+    }
+}
+
+// `id` holds the other parts of IDs, whose unit is the precision of their time.
+#[derive(Debug, PartialEq)]
+pub struct Parsed {
+    pub dt: DateTime<Utc>,
+    pub unit: Unit,
+    pub guessed: bool,
+    pub id: Option<IdParts>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct NumberParseError;
+
+/*
+ * An integer, possibly negative and with a decimal fraction (`-1758643530.123456`).
+ * The fraction is kept as digits rather than a float: it's exact down to the
+ * nanosecond whatever the unit, further digits are truncated.
+ * `negative` keeps the sign of values like `-0.5`, whose integer part is zero.
+*/
+#[derive(Debug, PartialEq)]
+pub struct Number {
+    pub int: i64,
+    pub frac: String,
+    pub negative: bool,
+}
+impl FromStr for Number {
+    type Err = NumberParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let (int, frac) = match s.split_once('.') {
+            Some((_, "")) => return Err(NumberParseError),
+            Some((int, frac)) => (int, frac),
+            None => (s, ""),
+        };
+        let all_digits = |x: &str| x.bytes().all(|b| b.is_ascii_digit());
+        if int.is_empty() || !all_digits(int) || !all_digits(frac) {
+            return Err(NumberParseError);
+        }
+        let int: i64 = int.parse().map_err(|_| NumberParseError)?;
+        Ok(Number {
+            int: if negative { -int } else { int },
+            frac: frac.into(),
+            negative,
+        })
+    }
+}
+impl Number {
+    pub fn to_dt(&self, unit: Unit) -> Option<DateTime<Utc>> {
+        if self.frac.is_empty() {
+            return unit.get_parser()(self.int);
+        }
+        let factor = unit.nanos();
+        let frac_len = factor.ilog10() as usize;
+        let frac_nanos: i128 = format!("{:0<frac_len$.frac_len$}", self.frac)
+            .parse()
+            .unwrap_or(0);
+        let nanos = self.int.unsigned_abs() as i128 * factor + frac_nanos;
+        let nanos = if self.negative { -nanos } else { nanos };
+        let secs = i64::try_from(nanos.div_euclid(1_000_000_000)).ok()?;
+        DateTime::from_timestamp(secs, nanos.rem_euclid(1_000_000_000) as u32)
+    }
+}
+
+pub fn ts_from_str(ts_str: String, opts: &ValueOptions) -> Result<Parsed, ValueError> {
+    let default_unit = if opts.auto { Unit::Auto } else { Unit::Secs };
+    let input = ParseResult::with_default(&ts_str, default_unit)?;
+    if let Ok(ts) = input.ts.parse::<Number>() {
+        let (unit, guessed) = match input.unit {
+            Unit::Auto => match opts.window.guess_unit(ts.int) {
+                Some(unit) => (unit, true),
+                None => return Err(ValueError::NotATS),
+            },
+            unit => (unit, false),
+        };
+        if let Some(dt) = ts.to_dt(unit) {
+            Ok(Parsed {
+                dt,
+                unit,
+                guessed,
+                id: None,
+            })
         } else {
-            Err(ValueError::NotAnInt)
+            Err(ValueError::NotATS)
         }
     } else {
-        Err(ValueError::Nothing)
+        Err(ValueError::NotAnInt)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::value::{ts_from_str, ValueError};
+    use crate::parse::InputParseError;
+    use crate::unit::Unit;
+    use crate::value::{ts_from_str, Number, NumberParseError, ValueError, ValueOptions};
     use chrono::{DateTime, Utc};
     use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -52,6 +177,29 @@ mod test {
 
         let err_nothing = ValueError::Nothing;
         assert_eq!(format!("{err_nothing}"), "can't interpret the value");
+
+        let err_not_a_date = ValueError::NotADate;
+        assert_eq!(format!("{err_not_a_date}"), "the value is not a date");
+
+        let err_not_an_expr = ValueError::NotAnExpr;
+        assert_eq!(format!("{err_not_an_expr}"), "the expression is invalid");
+
+        let err_not_an_id = ValueError::NotAnId;
+        assert_eq!(
+            format!("{err_not_an_id}"),
+            "the value is not a supported ID"
+        );
+    }
+
+    #[test]
+    fn test_value_code() {
+        assert_eq!(ValueError::NotAnInt.code(), "not_an_integer");
+        assert_eq!(ValueError::NotATS.code(), "not_a_timestamp");
+        assert_eq!(ValueError::Nothing.code(), "invalid_input");
+        assert_eq!(ValueError::NotADate.code(), "not_a_date");
+        assert_eq!(ValueError::NotAnId.code(), "not_an_id");
+        assert_eq!(ValueError::NotAnExpr.code(), "invalid_expression");
+        assert_eq!(ValueError::from(InputParseError), ValueError::Nothing);
     }
 
     #[test]
@@ -61,25 +209,162 @@ mod test {
         let ns_ts = now.duration_since(UNIX_EPOCH).unwrap().as_nanos();
         let ts_str = format!("n{}", ns_ts);
 
-        let dt = ts_from_str(ts_str).unwrap();
+        let dt = ts_from_str(ts_str, &ValueOptions::default()).unwrap().dt;
         assert_eq!(dt, unow);
     }
 
     #[test]
     fn test_ts_from_str_error_nothing() {
-        let error = ts_from_str("xxxx".to_string()).unwrap_err();
+        let error = ts_from_str("xxxx".to_string(), &ValueOptions::default()).unwrap_err();
         assert_eq!(error, ValueError::Nothing);
     }
 
     #[test]
     fn test_ts_from_str_error_not_an_integer() {
-        let error = ts_from_str("mxxxx".to_string()).unwrap_err();
+        let error = ts_from_str("mxxxx".to_string(), &ValueOptions::default()).unwrap_err();
         assert_eq!(error, ValueError::NotAnInt);
     }
 
     #[test]
     fn test_ts_from_str_error_not_a_timestamp() {
-        let error = ts_from_str("s100000000000000000".to_string()).unwrap_err();
+        let error =
+            ts_from_str("s100000000000000000".to_string(), &ValueOptions::default()).unwrap_err();
+        assert_eq!(error, ValueError::NotATS);
+    }
+
+    #[test]
+    fn test_ts_from_str_auto() {
+        let opts = ValueOptions {
+            auto: true,
+            ..Default::default()
+        };
+        let parsed = ts_from_str("1758643530123".to_string(), &opts).unwrap();
+        assert_eq!(parsed.unit, Unit::Millis);
+        assert!(parsed.guessed);
+        let expected = ts_from_str("m1758643530123".to_string(), &opts).unwrap();
+        assert_eq!(parsed.dt, expected.dt);
+
+        let parsed = ts_from_str("u1758643530123".to_string(), &opts).unwrap();
+        assert_eq!(parsed.unit, Unit::Micros);
+        assert!(!parsed.guessed);
+    }
+
+    #[test]
+    fn test_ts_from_str_auto_prefix() {
+        let parsed =
+            ts_from_str("a1758643530123456".to_string(), &ValueOptions::default()).unwrap();
+        assert_eq!(parsed.unit, Unit::Micros);
+        assert!(parsed.guessed);
+
+        let parsed = ts_from_str("1758643530123".to_string(), &ValueOptions::default()).unwrap();
+        assert_eq!(parsed.unit, Unit::Secs);
+        assert!(!parsed.guessed);
+    }
+
+    #[test]
+    fn test_ts_from_str_auto_error_not_a_timestamp() {
+        let error = ts_from_str("a1337".to_string(), &ValueOptions::default()).unwrap_err();
+        assert_eq!(error, ValueError::NotATS);
+    }
+
+    #[test]
+    fn test_number_from_str() {
+        let n: Number = "1758643530.0123".parse().unwrap();
+        assert_eq!(
+            n,
+            Number {
+                int: 1758643530,
+                frac: "0123".to_string(),
+                negative: false
+            }
+        );
+        let n: Number = "1337".parse().unwrap();
+        assert_eq!(n.frac, "".to_string());
+
+        let n: Number = "-0.5".parse().unwrap();
+        assert_eq!(
+            n,
+            Number {
+                int: 0,
+                frac: "5".to_string(),
+                negative: true
+            }
+        );
+
+        for s in [
+            "", ".5", "1.", "1.2.3", "1.x", "1.-2", "x.5", "-", "--1", "-+1", "+1",
+        ] {
+            assert_eq!(s.parse::<Number>().unwrap_err(), NumberParseError);
+        }
+    }
+
+    #[test]
+    fn test_ts_from_str_fraction() {
+        let opts = ValueOptions::default();
+        let table = [
+            ("1758643530.123456", 1758643530, 123_456_000),
+            ("1758643530.1234567891", 1758643530, 123_456_789),
+            ("m1758643530123.456789", 1758643530, 123_456_789),
+            ("u1758643530123456.7", 1758643530, 123_456_700),
+            ("n1758643530123456789.9", 1758643530, 123_456_789),
+            ("0.000000001", 0, 1),
+        ];
+        for (ts_str, secs, nanos) in table {
+            let dt = ts_from_str(ts_str.to_string(), &opts).unwrap().dt;
+            assert_eq!(
+                dt,
+                DateTime::from_timestamp(secs, nanos).unwrap(),
+                "{ts_str}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_ts_from_str_fraction_auto() {
+        let opts = ValueOptions {
+            auto: true,
+            ..Default::default()
+        };
+        let parsed = ts_from_str("1758643530123.5".to_string(), &opts).unwrap();
+        assert_eq!(parsed.unit, Unit::Millis);
+        assert_eq!(
+            parsed.dt,
+            DateTime::from_timestamp(1758643530, 123_500_000).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_ts_from_str_negative() {
+        let opts = ValueOptions::default();
+        let table = [
+            ("-86400", -86400, 0),
+            ("m-1000", -1, 0),
+            ("-m1000", -1, 0),
+            ("-u1", -1, 999_999_000),
+            ("n-1", -1, 999_999_999),
+            ("-0.5", -1, 500_000_000),
+            ("-1.25", -2, 750_000_000),
+            ("-m1500.5", -2, 499_500_000),
+        ];
+        for (ts_str, secs, nanos) in table {
+            let parsed = ts_from_str(ts_str.to_string(), &opts).unwrap();
+            assert_eq!(
+                parsed.dt,
+                DateTime::from_timestamp(secs, nanos).unwrap(),
+                "{ts_str}"
+            );
+        }
+        for ts_str in ["-m-1000", "--1", "m--1"] {
+            assert!(ts_from_str(ts_str.to_string(), &opts).is_err(), "{ts_str}");
+        }
+    }
+
+    #[test]
+    fn test_ts_from_str_fraction_error() {
+        let opts = ValueOptions::default();
+        let error = ts_from_str("1758643530.".to_string(), &opts).unwrap_err();
+        assert_eq!(error, ValueError::NotAnInt);
+        let error = ts_from_str("s100000000000000000.5".to_string(), &opts).unwrap_err();
         assert_eq!(error, ValueError::NotATS);
     }
 }
@@ -0,0 +1,107 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use std::str::FromStr;
+
+use crate::unit::Unit;
+
+const DEFAULT_START_YEAR: i32 = 2000;
+const DEFAULT_END_YEAR: i32 = 2100;
+
+#[derive(Debug, PartialEq)]
+pub struct WindowParseError;
+impl std::fmt::Display for WindowParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "expected <FROM>..<TO> years, FROM before TO")
+    }
+}
+impl std::error::Error for WindowParseError {}
+
+/*
+ * Range of dates a bare integer is expected to land in when it's really a
+ * timestamp, used to tell its granularity from its magnitude.
+ * Written as `<FROM>..<TO>` years on the command line, `TO` being excluded.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+impl Default for Window {
+    fn default() -> Self {
+        Window::from_years(DEFAULT_START_YEAR, DEFAULT_END_YEAR).unwrap()
+    }
+}
+impl FromStr for Window {
+    type Err = WindowParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.split_once("..").ok_or(WindowParseError)?;
+        let start: i32 = start.trim().parse().map_err(|_| WindowParseError)?;
+        let end: i32 = end.trim().parse().map_err(|_| WindowParseError)?;
+        Window::from_years(start, end)
+    }
+}
+impl Window {
+    fn from_years(start: i32, end: i32) -> Result<Window, WindowParseError> {
+        let year_start = |y: i32| {
+            NaiveDate::from_ymd_opt(y, 1, 1)
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .map(|d| d.and_utc())
+                .ok_or(WindowParseError)
+        };
+        if start >= end {
+            return Err(WindowParseError);
+        }
+        Ok(Window {
+            start: year_start(start)?,
+            end: year_start(end)?,
+        })
+    }
+
+    pub fn contains(&self, dt: &DateTime<Utc>) -> bool {
+        self.start <= *dt && *dt < self.end
+    }
+
+    // The coarsest granularity landing in the window wins.
+    pub fn guess_unit(&self, ts: i64) -> Option<Unit> {
+        [Unit::Secs, Unit::Millis, Unit::Micros, Unit::Nanos]
+            .into_iter()
+            .find(|unit| match unit.get_parser()(ts) {
+                Some(dt) => self.contains(&dt),
+                None => false,
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::unit::Unit;
+    use crate::window::{Window, WindowParseError};
+
+    use chrono::{TimeZone, Utc};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_from_str() {
+        let w = Window::from_str("1990..2050").unwrap();
+        assert_eq!(w.start, Utc.with_ymd_and_hms(1990, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(w.end, Utc.with_ymd_and_hms(2050, 1, 1, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_from_str_err() {
+        for s in ["", "1990", "1990..", "x..2000", "2050..1990", "2000..2000"] {
+            assert_eq!(Window::from_str(s).unwrap_err(), WindowParseError);
+        }
+    }
+
+    #[test]
+    fn test_guess_unit() {
+        let w = Window::default();
+        assert_eq!(w.guess_unit(1758643530), Some(Unit::Secs));
+        assert_eq!(w.guess_unit(1758643530123), Some(Unit::Millis));
+        assert_eq!(w.guess_unit(1758643530123456), Some(Unit::Micros));
+        assert_eq!(w.guess_unit(1758643530123456789), Some(Unit::Nanos));
+        assert_eq!(w.guess_unit(1337), None);
+        assert_eq!(w.guess_unit(17586435301), None);
+    }
+}
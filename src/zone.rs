@@ -0,0 +1,362 @@
+use chrono::{FixedOffset, Local, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::{Tz, TzOffset, TZ_VARIANTS};
+use std::str::FromStr;
+use std::{env, fs};
+
+use crate::posix::PosixTz;
+
+const MAX_SUGGESTIONS: usize = 3;
+const LOCAL: &str = "local";
+const LOCALTIME: &str = "/etc/localtime";
+const OFFSET_PREFIXES: &[&str] = &["UTC", "GMT"];
+
+#[derive(Debug, PartialEq)]
+pub struct ZoneParseError;
+impl std::fmt::Display for ZoneParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown timezone")
+    }
+}
+impl std::error::Error for ZoneParseError {}
+
+/*
+ * A timezone as given to -T: an IANA name, a fixed offset (`+05:30`, `UTC-8`),
+ * a POSIX TZ string (`EST5EDT,M3.2.0,M11.1.0`), or `local` for the system one.
+ * Unlike POSIX ones, `UTC-8` and `GMT-8` offsets are behind UTC.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Zone {
+    Named(Tz),
+    Fixed(FixedOffset),
+    Posix(PosixTz),
+    // the system timezone, when it couldn't be resolved to a named one
+    Local,
+}
+impl FromStr for Zone {
+    type Err = ZoneParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case(LOCAL) {
+            return Ok(local_zone());
+        }
+        if let Ok(tz) = s.parse::<Tz>() {
+            return Ok(Zone::Named(tz));
+        }
+        if let Some(offset) = parse_offset(s) {
+            return Ok(Zone::Fixed(offset));
+        }
+        match s.parse::<PosixTz>() {
+            Ok(posix) => Ok(Zone::Posix(posix)),
+            Err(_) => Err(ZoneParseError),
+        }
+    }
+}
+impl Zone {
+    // UTC under one of its usual names, or a zero offset.
+    pub fn is_utc(&self) -> bool {
+        match self {
+            Zone::Named(tz) => matches!(
+                tz.name(),
+                "UTC" | "Etc/UTC" | "GMT" | "Etc/GMT" | "Universal" | "Etc/Universal" | "Zulu"
+            ),
+            Zone::Fixed(offset) => offset.local_minus_utc() == 0,
+            _ => false,
+        }
+    }
+}
+impl From<Tz> for Zone {
+    fn from(tz: Tz) -> Self {
+        Zone::Named(tz)
+    }
+}
+impl std::fmt::Display for Zone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Zone::Named(tz) => write!(f, "{}", tz.name()),
+            Zone::Fixed(offset) => write!(f, "{offset}"),
+            Zone::Posix(posix) => write!(f, "{}", posix.spec()),
+            Zone::Local => write!(f, "{LOCAL}"),
+        }
+    }
+}
+
+// Displayed by `%Z`: the abbreviation when there's one, the offset otherwise.
+#[derive(Debug, Clone)]
+pub enum ZoneOffset {
+    Named(TzOffset),
+    Fixed(FixedOffset),
+    Posix(FixedOffset, String),
+}
+impl Offset for ZoneOffset {
+    fn fix(&self) -> FixedOffset {
+        match self {
+            ZoneOffset::Named(offset) => offset.fix(),
+            ZoneOffset::Fixed(offset) | ZoneOffset::Posix(offset, _) => *offset,
+        }
+    }
+}
+impl std::fmt::Display for ZoneOffset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ZoneOffset::Named(offset) => write!(f, "{offset}"),
+            ZoneOffset::Fixed(offset) => write!(f, "{offset}"),
+            ZoneOffset::Posix(_, abbr) => write!(f, "{abbr}"),
+        }
+    }
+}
+
+impl TimeZone for Zone {
+    type Offset = ZoneOffset;
+
+    fn from_offset(offset: &ZoneOffset) -> Self {
+        match offset {
+            ZoneOffset::Named(offset) => Zone::Named(Tz::from_offset(offset)),
+            ZoneOffset::Fixed(offset) | ZoneOffset::Posix(offset, _) => Zone::Fixed(*offset),
+        }
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<ZoneOffset> {
+        self.offset_from_local_datetime(&local.and_time(Default::default()))
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<ZoneOffset> {
+        match self {
+            Zone::Named(tz) => tz.offset_from_local_datetime(local).map(ZoneOffset::Named),
+            Zone::Fixed(offset) => LocalResult::Single(ZoneOffset::Fixed(*offset)),
+            Zone::Posix(posix) => posix
+                .offset_from_local(local)
+                .map(|(offset, abbr)| ZoneOffset::Posix(offset, abbr.to_string())),
+            Zone::Local => Local
+                .offset_from_local_datetime(local)
+                .map(ZoneOffset::Fixed),
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> ZoneOffset {
+        self.offset_from_utc_datetime(&utc.and_time(Default::default()))
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> ZoneOffset {
+        match self {
+            Zone::Named(tz) => ZoneOffset::Named(tz.offset_from_utc_datetime(utc)),
+            Zone::Fixed(offset) => ZoneOffset::Fixed(*offset),
+            Zone::Posix(posix) => {
+                let (offset, abbr) = posix.offset_at(utc);
+                ZoneOffset::Posix(offset, abbr.to_string())
+            }
+            Zone::Local => ZoneOffset::Fixed(Local.offset_from_utc_datetime(utc)),
+        }
+    }
+}
+
+// `+05:30`, `+0530`, `-8`, optionally prefixed with UTC or GMT.
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let upper = s.to_ascii_uppercase();
+    let s = OFFSET_PREFIXES
+        .iter()
+        .find_map(|prefix| upper.strip_prefix(prefix))
+        .unwrap_or(upper.as_str());
+    let (sign, hm) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let (h, m) = match hm.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if hm.len() == 4 => hm.split_at(2),
+        None => (hm, "00"),
+    };
+    if h.is_empty() || h.len() > 2 || m.len() != 2 {
+        return None;
+    }
+    if !h.bytes().chain(m.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (h, m): (i32, i32) = (h.parse().ok()?, m.parse().ok()?);
+    if m >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (h * 3600 + m * 60))
+}
+
+// The TZ variable first, then the zone /etc/localtime links to, then whatever the system says.
+fn local_zone() -> Zone {
+    if let Ok(tz) = env::var("TZ") {
+        let tz = tz.strip_prefix(':').unwrap_or(&tz);
+        let zone = (!tz.eq_ignore_ascii_case(LOCAL))
+            .then(|| tz.parse::<Zone>().ok())
+            .flatten();
+        if let Some(zone) = zone {
+            return zone;
+        }
+    }
+    if let Ok(path) = fs::read_link(LOCALTIME) {
+        let path = path.to_string_lossy();
+        let tz = path
+            .split_once("zoneinfo/")
+            .and_then(|(_, name)| name.parse::<Tz>().ok());
+        if let Some(tz) = tz {
+            return Zone::Named(tz);
+        }
+    }
+    Zone::Local
+}
+
+/*
+ * Timezone names close to a mistyped one, best first.
+ * Names are compared case insensitively, in full and by their last part,
+ * so that a bare city name (`Tokyo`) finds its zone (`Asia/Tokyo`).
+ * Names much further than the closest one aren't worth suggesting.
+*/
+pub fn suggest(name: &str) -> Vec<&'static str> {
+    let name = name.to_lowercase();
+    let max_distance = (name.chars().count() / 3).max(2);
+    let mut candidates: Vec<(usize, &'static str)> = TZ_VARIANTS
+        .iter()
+        .map(|tz| tz.name())
+        .filter_map(|variant| {
+            let lower = variant.to_lowercase();
+            let last = lower.rsplit('/').next().unwrap_or(&lower);
+            let distance = distance(&name, &lower).min(distance(&name, last));
+            (distance <= max_distance).then_some((distance, variant))
+        })
+        .collect();
+    candidates.sort();
+    let best = candidates.first().map(|(d, _)| *d).unwrap_or(0);
+    candidates
+        .into_iter()
+        .take_while(|(d, _)| *d <= best + 1)
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, variant)| variant)
+        .collect()
+}
+
+// Levenshtein distance.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev_diag = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { prev_diag } else { prev_diag + 1 };
+            prev_diag = row[j + 1];
+            row[j + 1] = cost.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::zone::{distance, parse_offset, suggest, Zone, ZoneParseError};
+
+    use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("", ""), 0);
+        assert_eq!(distance("paris", ""), 5);
+        assert_eq!(distance("paris", "paris"), 0);
+        assert_eq!(distance("pari", "paris"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_suggest() {
+        assert_eq!(suggest("Europe/Pari")[0], "Europe/Paris");
+        assert_eq!(suggest("europe/paris")[0], "Europe/Paris");
+        assert_eq!(suggest("Tokio")[0], "Asia/Tokyo");
+        assert!(suggest("America/New_Yrok").contains(&"America/New_York"));
+        assert!(suggest("Atlantis/Atlantis_City").is_empty());
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        let table = [
+            ("+05:30", 19800),
+            ("+0530", 19800),
+            ("-08", -28800),
+            ("-8", -28800),
+            ("UTC-8", -28800),
+            ("utc+5:30", 19800),
+            ("GMT+00:00", 0),
+        ];
+        for (s, secs) in table {
+            assert_eq!(parse_offset(s), FixedOffset::east_opt(secs), "{s}");
+        }
+        for s in [
+            "", "+", "5", "UTC", "+123", "+05:3", "+05:60", "+24", "+x", "UTC+-1",
+        ] {
+            assert_eq!(parse_offset(s), None, "{s}");
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        let zone: Zone = "Europe/Paris".parse().unwrap();
+        assert_eq!(zone, Zone::Named(chrono_tz::Europe::Paris));
+        let zone: Zone = "UTC-8".parse().unwrap();
+        assert_eq!(zone, Zone::Fixed(FixedOffset::west_opt(28800).unwrap()));
+        let zone: Zone = "CET-1CEST,M3.5.0,M10.5.0/3".parse().unwrap();
+        assert!(matches!(zone, Zone::Posix(_)));
+        assert!("local".parse::<Zone>().is_ok());
+        assert_eq!("Europe/Pari".parse::<Zone>().unwrap_err(), ZoneParseError);
+    }
+
+    #[test]
+    fn test_is_utc() {
+        for s in ["UTC", "Etc/GMT", "+00:00", "UTC+0"] {
+            assert!(s.parse::<Zone>().unwrap().is_utc(), "{s}");
+        }
+        for s in ["Europe/London", "+01:00", "UTC0"] {
+            assert!(!s.parse::<Zone>().unwrap().is_utc(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let zones = [
+            "Europe/Paris",
+            "+05:30",
+            "<+0530>-5:30",
+            "EST5EDT,M3.2.0,M11.1.0",
+        ];
+        for s in zones {
+            assert_eq!(format!("{}", s.parse::<Zone>().unwrap()), s);
+        }
+        assert_eq!(format!("{}", Zone::Local), "local");
+    }
+
+    #[test]
+    fn test_format() {
+        let dt: DateTime<Utc> = DateTime::from_timestamp(1758643530, 0).unwrap();
+        let table = [
+            ("Europe/Paris", "18:05:30 +02:00 CEST"),
+            ("+05:30", "21:35:30 +05:30 +05:30"),
+            ("UTC-8", "08:05:30 -08:00 -08:00"),
+            ("CET-1CEST,M3.5.0,M10.5.0/3", "18:05:30 +02:00 CEST"),
+            ("<+0530>-5:30", "21:35:30 +05:30 +0530"),
+        ];
+        for (s, expected) in table {
+            let zone: Zone = s.parse().unwrap();
+            let got = dt
+                .with_timezone(&zone)
+                .format("%H:%M:%S %:z %Z")
+                .to_string();
+            assert_eq!(got, expected, "{s}");
+        }
+    }
+
+    #[test]
+    fn test_local() {
+        let dt: DateTime<Utc> = DateTime::from_timestamp(1758643530, 0).unwrap();
+        let local = dt.with_timezone(&Zone::Local);
+        assert_eq!(local.to_utc(), dt);
+        let naive = local.naive_local();
+        assert_eq!(
+            Zone::Local.from_local_datetime(&naive).earliest().unwrap(),
+            local
+        );
+    }
+}